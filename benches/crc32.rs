@@ -0,0 +1,50 @@
+//! Benchmark of the CRC32 calculation against a bitwise implementation.
+//!
+//! Run with `cargo bench --features crc32-slice8` to measure the
+//! slice-by-8 variant instead of the byte-wise table lookup.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use dfufile::crc32::crc32;
+
+/// Buffer size used for the benchmark.
+const BUFFER_SIZE: usize = 16 * 1024 * 1024;
+
+/// Reference implementation without lookup table.
+fn crc32_bitwise(buffer: &[u8], initial: u32) -> u32 {
+    let mut result = initial ^ 0xFFFFFFFF_u32;
+
+    for byte in buffer {
+        result ^= *byte as u32;
+
+        for _ in 0..8 {
+            let mask = (result & 1).wrapping_neg();
+            result = (result >> 1) ^ (0xEDB88320 & mask);
+        }
+    }
+
+    result ^ 0xFFFFFFFF_u32
+}
+
+fn bench_crc32(c: &mut Criterion) {
+    let buffer = (0..BUFFER_SIZE)
+        .map(|i| (i * 31 % 251) as u8)
+        .collect::<Vec<_>>();
+
+    assert_eq!(crc32(&buffer, 0), crc32_bitwise(&buffer, 0));
+
+    let mut group = c.benchmark_group("crc32");
+    group.throughput(Throughput::Bytes(BUFFER_SIZE as u64));
+    group.sample_size(10);
+
+    group.bench_function("bitwise", |b| {
+        b.iter(|| crc32_bitwise(black_box(&buffer), 0))
+    });
+
+    group.bench_function("table", |b| b.iter(|| crc32(black_box(&buffer), 0)));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_crc32);
+criterion_main!(benches);
@@ -0,0 +1,78 @@
+//! Benchmark of reading target prefixes with and without reusing
+//! the allocations of a previous target prefix.
+//!
+//! A DfuSe file can hold at most 255 targets, so the target prefixes
+//! are read from a synthetic stream instead of a complete file.
+
+use std::io::Cursor;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use dfufile::dfuse::TargetPrefix;
+
+/// Number of target prefixes in the stream.
+const TARGET_COUNT: usize = 1000;
+
+fn bench_target_prefix(c: &mut Criterion) {
+    let mut stream = Vec::with_capacity(TARGET_COUNT * TargetPrefix::LENGTH);
+
+    for index in 0..TARGET_COUNT {
+        let mut target_prefix = TargetPrefix {
+            bAlternateSetting: index as u8,
+            ..Default::default()
+        };
+        target_prefix
+            .set_name(&format!("@Internal Flash {index} /0x08000000/04*016Kg"))
+            .unwrap();
+        stream.extend_from_slice(&target_prefix.to_bytes());
+    }
+
+    let mut target_prefix = TargetPrefix::default();
+    target_prefix
+        .update_from_file(
+            &mut Cursor::new(&stream),
+            &mut 0,
+            &mut [0; TargetPrefix::LENGTH],
+        )
+        .unwrap();
+    assert_eq!(
+        target_prefix,
+        TargetPrefix::from_file(&mut Cursor::new(&stream), &mut 0).unwrap()
+    );
+
+    let mut group = c.benchmark_group("target_prefix");
+    group.throughput(Throughput::Elements(TARGET_COUNT as u64));
+
+    group.bench_function("from_file", |b| {
+        b.iter(|| {
+            let mut file = Cursor::new(black_box(&stream));
+            let mut file_pos = 0;
+
+            for _ in 0..TARGET_COUNT {
+                black_box(TargetPrefix::from_file(&mut file, &mut file_pos).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("update_from_file", |b| {
+        let mut target_prefix = TargetPrefix::default();
+        let mut buffer = [0; TargetPrefix::LENGTH];
+
+        b.iter(|| {
+            let mut file = Cursor::new(black_box(&stream));
+            let mut file_pos = 0;
+
+            for _ in 0..TARGET_COUNT {
+                target_prefix
+                    .update_from_file(&mut file, &mut file_pos, &mut buffer)
+                    .unwrap();
+                black_box(&target_prefix);
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_target_prefix);
+criterion_main!(benches);
@@ -1,13 +1,456 @@
 //! Dumps the structure of the file given as argument
+//!
+//! Usage: `dfufile-dump [--expect-vid <id>] [--expect-pid <id>] [--preview[=<n>]] [--suffix-only] <file>`
+//!
+//! When an expected vendor or product id is given in hexadecimal notation,
+//! a verdict is printed if the file is compatible with such a device.
+//! With `--preview=<n>`, the first `n` bytes of each element of a DfuSe
+//! file are printed as hex dump, `--preview` alone prints 64 bytes.
+//! With `--suffix-only`, only the suffix fields are printed without
+//! parsing the rest of the file.
+//!
+//! Usage: `dfufile-dump verify <file>`
+//!
+//! Checks the file for consistency and exits with an error code
+//! if problems are found.
+//!
+//! Usage: `dfufile-dump manifest <file>`
+//!
+//! Prints a JSON manifest listing the alternate setting and name of each
+//! image and the address, size and SHA-256 hash of each element. For plain
+//! files, the size and hash of the payload are listed instead. The output
+//! only depends on the file content, so it can be kept under version control
+//! to detect changes.
+//!
+//! Usage: `dfufile-dump extract-all <file> [--outdir <dir>]`
+//!
+//! Writes the data of each element of a DfuSe file into a separate file
+//! in the output directory, which defaults to the current directory.
+//! The files are named `<image index>_alt<alt setting>_<target name>_<address>.bin`,
+//! e.g. `0_alt0_Internal_Flash_0x08000000.bin`. Characters other than ASCII
+//! letters, digits and `-` are replaced by `_` in the target name,
+//! and the name is omitted if the target is not named. Extraction fails
+//! if two elements of an image start at the same address.
 
-use dfufile::DfuFile;
+use dfufile::{Content, DfuFile, Suffix};
+use sha2::{Digest, Sha256};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let path = std::env::args().nth(1).expect("No file given");
+    let mut args = std::env::args().skip(1).peekable();
+
+    match args.peek().map(String::as_str) {
+        Some("verify") => {
+            args.next();
+            verify(args)
+        }
+        Some("extract-all") => {
+            args.next();
+            extract_all(args)
+        }
+        Some("manifest") => {
+            args.next();
+            manifest(args)
+        }
+        _ => dump(args),
+    }
+}
+
+/// Default number of bytes shown by the element preview.
+const DEFAULT_PREVIEW_SIZE: usize = 64;
+
+/// Dumps the structure of the file.
+fn dump(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut path = None;
+    let mut expect_vid = None;
+    let mut expect_pid = None;
+    let mut preview = None;
+    let mut suffix_only = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--expect-vid" => expect_vid = Some(parse_id(args.next())?),
+            "--expect-pid" => expect_pid = Some(parse_id(args.next())?),
+            "--preview" => preview = Some(DEFAULT_PREVIEW_SIZE),
+            "--suffix-only" => suffix_only = true,
+            _ => match arg.strip_prefix("--preview=") {
+                Some(size) => preview = Some(size.parse().map_err(|_| "Invalid preview size")?),
+                None => path = Some(arg),
+            },
+        }
+    }
+
+    let path = path.expect("No file given");
+
+    if suffix_only {
+        return dump_suffix(&path);
+    }
+
     let mut dfu_file = DfuFile::open(path)?;
 
     println!("{dfu_file:#?}");
     println!("Calculated CRC32: {:?}", &mut dfu_file.calc_crc());
 
+    print_ids(&dfu_file.suffix);
+
+    if expect_vid.is_some() || expect_pid.is_some() {
+        let suffix = &dfu_file.suffix;
+        let vendor = expect_vid.unwrap_or(suffix.idVendor);
+        let product = expect_pid.unwrap_or(suffix.idProduct);
+
+        if suffix.matches_device(vendor, product) {
+            println!("Compatible with device {vendor:04x}:{product:04x}");
+        } else {
+            println!("Not compatible with device {vendor:04x}:{product:04x}");
+        }
+    }
+
+    if let (Some(preview_size), Content::DfuSe(content)) = (preview, &dfu_file.content) {
+        for image in &content.images {
+            for element in &image.image_elements {
+                let size = std::cmp::min(preview_size, element.dwElementSize as usize);
+                let mut buffer = vec![0; size];
+                let read_size = element.read_at(&mut dfu_file.file, 0, &mut buffer)?;
+
+                println!(
+                    "\nAlt {}, element at 0x{:08X} ({} bytes):",
+                    image.target_prefix.bAlternateSetting,
+                    element.dwElementAddress,
+                    element.dwElementSize
+                );
+                print_hex_dump(element.dwElementAddress, &buffer[..read_size]);
+            }
+        }
+    }
+
     Ok(())
 }
+
+/// Prints the suffix fields without parsing the rest of the file.
+fn dump_suffix(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = std::fs::File::open(path)?;
+    let suffix = Suffix::from_file(&mut file)?;
+    let crc_valid = dfufile::verify_file_crc(path)?;
+
+    print_ids(&suffix);
+    println!("DFU specification: 0x{:04x}", suffix.bcdDFU);
+    println!(
+        "CRC32: 0x{:08X} ({})",
+        suffix.dwCRC,
+        if crc_valid { "valid" } else { "invalid" }
+    );
+
+    Ok(())
+}
+
+/// Print the vendor id, product id and firmware version of the suffix.
+fn print_ids(suffix: &Suffix) {
+    match suffix.vendor_id() {
+        Some(id) => match id.vendor_name() {
+            Some(name) => println!("Vendor: {id} ({name})"),
+            None => println!("Vendor: {id}"),
+        },
+        None => println!("Vendor: any"),
+    }
+
+    match suffix.product_id() {
+        Some(id) => println!("Product: {id}"),
+        None => println!("Product: any"),
+    }
+
+    match suffix.firmware_version() {
+        Some((major, minor)) => println!("Firmware version: v{major}.{minor:02}"),
+        None if suffix.bcdDevice == 0xFFFF => println!("Firmware version: any"),
+        None => println!("Firmware version: 0x{:04x}", suffix.bcdDevice),
+    }
+}
+
+/// Print data as hex dump with 16 bytes per line, starting at `address`.
+fn print_hex_dump(address: u32, data: &[u8]) {
+    for (index, line) in data.chunks(16).enumerate() {
+        let hex = line
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = line
+            .iter()
+            .map(|byte| {
+                if byte.is_ascii_graphic() || *byte == b' ' {
+                    *byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+
+        println!(
+            "{:08X}  {hex:<47}  |{ascii}|",
+            address.wrapping_add(index as u32 * 16)
+        );
+    }
+}
+
+/// Checks the file for consistency.
+fn verify(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = args.next().expect("No file given");
+    let mut dfu_file = DfuFile::open(path)?;
+    let report = dfu_file.analyze()?;
+    let mut errors = 0;
+
+    if !report.crc_valid {
+        println!(
+            "Error: CRC32 mismatch, stored 0x{:08X}, calculated 0x{:08X}",
+            dfu_file.suffix.dwCRC, report.crc
+        );
+        errors += 1;
+    }
+
+    if let (false, Content::DfuSe(content)) = (report.declared_size_matches, &dfu_file.content) {
+        println!(
+            "Error: Image size mismatch, declared {} bytes, computed {}",
+            content.prefix.DFUImageSize,
+            content.computed_file_size() - Suffix::LENGTH as u64
+        );
+        errors += 1;
+    }
+
+    for image in &report.images {
+        if image.declared_size as u64 != image.computed_size {
+            println!(
+                "Error: Target size mismatch for alternate setting {}, declared {} bytes, computed {}",
+                image.alt_setting, image.declared_size, image.computed_size
+            );
+            errors += 1;
+        }
+    }
+
+    for alt_setting in &report.duplicate_alts {
+        println!("Error: Alternate setting {alt_setting} is used by multiple images");
+        errors += 1;
+    }
+
+    for (element, other) in &report.overlaps {
+        println!(
+            "Error: Elements at 0x{:08X} and 0x{:08X} in alternate setting {} overlap",
+            element.address, other.address, element.alt_setting
+        );
+        errors += 1;
+    }
+
+    for image in &report.images {
+        for address in &image.empty_elements {
+            println!(
+                "Warning: Element at 0x{address:08X} in alternate setting {} has a size of zero",
+                image.alt_setting
+            );
+        }
+    }
+
+    if report.missing_bytes > 0 {
+        println!(
+            "Error: File is {} bytes shorter than required by the structure",
+            report.missing_bytes
+        );
+        errors += 1;
+    }
+
+    if report.trailing_gap > 0 {
+        println!(
+            "Warning: {} bytes of data between last element and suffix",
+            report.trailing_gap
+        );
+    }
+
+    if errors > 0 {
+        println!("Verification failed with {errors} error(s)");
+        std::process::exit(1);
+    }
+
+    println!("Verification passed");
+
+    Ok(())
+}
+
+/// Extracts the data of all elements into separate files.
+fn extract_all(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut path = None;
+    let mut outdir = std::path::PathBuf::from(".");
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--outdir" => outdir = args.next().ok_or("Missing output directory")?.into(),
+            _ => path = Some(arg),
+        }
+    }
+
+    let path = path.expect("No file given");
+    let mut dfu_file = DfuFile::open(path)?;
+
+    let content = match &dfu_file.content {
+        Content::DfuSe(content) => content,
+        Content::Plain => return Err("File is not a DfuSe file".into()),
+    };
+
+    std::fs::create_dir_all(&outdir)?;
+
+    let mut file_names = std::collections::HashSet::new();
+
+    for (index, image) in content.images.iter().enumerate() {
+        let target_prefix = &image.target_prefix;
+        let name = sanitize_name(&target_prefix.szTargetName);
+
+        for element in &image.image_elements {
+            let file_name = if name.is_empty() {
+                format!(
+                    "{}_alt{}_0x{:08X}.bin",
+                    index, target_prefix.bAlternateSetting, element.dwElementAddress
+                )
+            } else {
+                format!(
+                    "{}_alt{}_{}_0x{:08X}.bin",
+                    index, target_prefix.bAlternateSetting, name, element.dwElementAddress
+                )
+            };
+
+            // Elements of an image starting at the same address get the same name
+            if !file_names.insert(file_name.clone()) {
+                return Err(format!("Duplicate file name {file_name}").into());
+            }
+
+            let data = element.read_all(&mut dfu_file.file)?;
+            std::fs::write(outdir.join(&file_name), data)?;
+
+            println!("{file_name}: {} bytes", element.dwElementSize);
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints a JSON manifest of the file content.
+fn manifest(mut args: impl Iterator<Item = String>) -> Result<(), Box<dyn std::error::Error>> {
+    let path = args.next().expect("No file given");
+    let mut dfu_file = DfuFile::open(path)?;
+
+    println!("{{");
+
+    match &dfu_file.content {
+        Content::Plain => {
+            let payload = dfu_file.plain_payload()?;
+
+            println!("  \"format\": \"plain\",");
+            println!("  \"payload\": {{");
+            println!("    \"size\": {},", payload.len());
+            println!("    \"sha256\": \"{}\"", to_hex(&sha256(&payload)));
+            println!("  }}");
+        }
+        Content::DfuSe(content) => {
+            println!("  \"format\": \"dfuse\",");
+            println!("  \"images\": [");
+
+            for (image_index, image) in content.images.iter().enumerate() {
+                println!("    {{");
+                println!(
+                    "      \"alt_setting\": {},",
+                    image.target_prefix.bAlternateSetting
+                );
+                println!(
+                    "      \"name\": \"{}\",",
+                    escape_json(&image.target_prefix.szTargetName)
+                );
+                println!("      \"elements\": [");
+
+                for (element_index, element) in image.image_elements.iter().enumerate() {
+                    let data = element.read_all(&mut dfu_file.file)?;
+
+                    println!("        {{");
+                    println!(
+                        "          \"address\": \"0x{:08X}\",",
+                        element.dwElementAddress
+                    );
+                    println!("          \"size\": {},", element.dwElementSize);
+                    println!("          \"sha256\": \"{}\"", to_hex(&sha256(&data)));
+                    println!(
+                        "        }}{}",
+                        separator(element_index, image.image_elements.len())
+                    );
+                }
+
+                println!("      ]");
+                println!("    }}{}", separator(image_index, content.images.len()));
+            }
+
+            println!("  ]");
+        }
+    }
+
+    println!("}}");
+
+    Ok(())
+}
+
+/// Returns the separator following the JSON array item at `index`.
+fn separator(index: usize, len: usize) -> &'static str {
+    if index + 1 < len {
+        ","
+    } else {
+        ""
+    }
+}
+
+/// Escape a string for use inside a JSON string literal.
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Format bytes as lowercase hex string.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Calculate the SHA-256 hash of data.
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// Make a target name safe for use in a file name.
+fn sanitize_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Parse a vendor or product id given in hexadecimal notation.
+fn parse_id(arg: Option<String>) -> Result<u16, Box<dyn std::error::Error>> {
+    let arg = arg.ok_or("Missing id value")?;
+    let digits = arg
+        .strip_prefix("0x")
+        .or_else(|| arg.strip_prefix("0X"))
+        .unwrap_or(&arg);
+    let id = u16::from_str_radix(digits, 16)?;
+
+    Ok(id)
+}
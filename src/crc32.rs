@@ -1,6 +1,10 @@
 //! CRC32 checksum calculation used in the file suffix.
 //!
 //! It is derived from work by Gary S. Brown.
+//!
+//! The calculation uses a lookup table processing one byte at a time.
+//! With the `crc32-slice8` feature enabled, eight bytes are processed at a
+//! time using additional tables, which is faster on large buffers.
 
 const CRC_TABLE: [u32; 256] = [
     0x00000000, 0x77073096, 0xee0e612c, 0x990951ba, 0x076dc419, 0x706af48f, 0xe963a535, 0x9e6495a3,
@@ -37,14 +41,142 @@ const CRC_TABLE: [u32; 256] = [
     0xb3667a2e, 0xc4614ab8, 0x5d681b02, 0x2a6f2b94, 0xb40bbe37, 0xc30c8ea1, 0x5a05df1b, 0x2d02ef8d,
 ];
 
+/// Standard CRC32 polynomial in reversed representation, used by DFU files.
+pub const DFU_POLYNOMIAL: u32 = 0xEDB88320;
+
 /// Returns the CRC32 of a buffer and an initial value
 pub fn crc32(buffer: &[u8], initial: u32) -> u32 {
-    let mut result = initial ^ 0xffffffff_u32;
+    crc32_with_poly(buffer, initial, DFU_POLYNOMIAL)
+}
+
+/// Returns the CRC32 of a buffer and an initial value using a custom polynomial.
+///
+/// The polynomial must be given in reversed representation, e.g. 0x82F63B78
+/// for CRC-32C. DFU files always use [`DFU_POLYNOMIAL`], other polynomials
+/// are only intended for checksums unrelated to DFU files and are calculated
+/// bitwise without lookup table.
+pub fn crc32_with_poly(buffer: &[u8], initial: u32, polynomial: u32) -> u32 {
+    let register = initial ^ 0xffffffff_u32;
+
+    let register = if polynomial == DFU_POLYNOMIAL {
+        update(register, buffer)
+    } else {
+        update_bitwise(register, buffer, polynomial)
+    };
+
+    register ^ 0xFFFFFFFF_u32
+}
+
+/// Updates the raw CRC register bit by bit using a custom polynomial.
+fn update_bitwise(mut register: u32, buffer: &[u8], polynomial: u32) -> u32 {
+    for byte in buffer {
+        register ^= *byte as u32;
+
+        for _ in 0..8 {
+            let mask = (register & 1).wrapping_neg();
+            register = (register >> 1) ^ (polynomial & mask);
+        }
+    }
 
+    register
+}
+
+/// Updates the raw CRC register with the content of a buffer.
+#[cfg(not(feature = "crc32-slice8"))]
+fn update(mut register: u32, buffer: &[u8]) -> u32 {
     for byte in buffer {
-        let index = byte ^ ((result & 0xFF) as u8);
-        result = (result >> 8) ^ CRC_TABLE[index as usize];
+        let index = byte ^ ((register & 0xFF) as u8);
+        register = (register >> 8) ^ CRC_TABLE[index as usize];
     }
 
-    result ^ 0xFFFFFFFF_u32
+    register
+}
+
+/// Updates the raw CRC register with the content of a buffer.
+#[cfg(feature = "crc32-slice8")]
+fn update(mut register: u32, buffer: &[u8]) -> u32 {
+    let mut chunks = buffer.chunks_exact(8);
+
+    for chunk in &mut chunks {
+        let low = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) ^ register;
+        let high = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+
+        register = SLICE_TABLES[7][(low & 0xFF) as usize]
+            ^ SLICE_TABLES[6][((low >> 8) & 0xFF) as usize]
+            ^ SLICE_TABLES[5][((low >> 16) & 0xFF) as usize]
+            ^ SLICE_TABLES[4][(low >> 24) as usize]
+            ^ SLICE_TABLES[3][(high & 0xFF) as usize]
+            ^ SLICE_TABLES[2][((high >> 8) & 0xFF) as usize]
+            ^ SLICE_TABLES[1][((high >> 16) & 0xFF) as usize]
+            ^ SLICE_TABLES[0][(high >> 24) as usize];
+    }
+
+    for byte in chunks.remainder() {
+        let index = byte ^ ((register & 0xFF) as u8);
+        register = (register >> 8) ^ CRC_TABLE[index as usize];
+    }
+
+    register
+}
+
+/// Lookup tables for processing eight bytes at a time.
+#[cfg(feature = "crc32-slice8")]
+const SLICE_TABLES: [[u32; 256]; 8] = make_slice_tables();
+
+/// Derives the tables for slice-by-8 processing from the base table.
+#[cfg(feature = "crc32-slice8")]
+const fn make_slice_tables() -> [[u32; 256]; 8] {
+    let mut tables = [[0; 256]; 8];
+    tables[0] = CRC_TABLE;
+
+    let mut index = 0;
+
+    while index < 256 {
+        let mut slice = 1;
+
+        while slice < 8 {
+            let previous = tables[slice - 1][index];
+            tables[slice][index] = (previous >> 8) ^ CRC_TABLE[(previous & 0xFF) as usize];
+            slice += 1;
+        }
+
+        index += 1;
+    }
+
+    tables
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Incremental CRC32 calculation over multiple buffers.
+#[derive(Debug, Clone)]
+pub struct Crc32 {
+    /// Raw CRC register without the final XOR applied.
+    register: u32,
+}
+
+impl Default for Crc32 {
+    /// Creates a new instance with the initial register value.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    /// Creates a new instance.
+    pub fn new() -> Self {
+        Self {
+            register: 0xFFFFFFFF_u32,
+        }
+    }
+
+    /// Feeds the content of a buffer into the calculation.
+    pub fn update(&mut self, buffer: &[u8]) {
+        self.register = update(self.register, buffer);
+    }
+
+    /// Returns the CRC32 of all data fed into the calculation.
+    pub fn finalize(self) -> u32 {
+        self.register ^ 0xFFFFFFFF_u32
+    }
 }
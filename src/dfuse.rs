@@ -2,29 +2,98 @@
 //!
 //! See document UM0391 Revision 1 for a detailed specification.
 
-use std::io::{Read, Seek};
+pub mod layout;
+pub mod stm32;
 
-use crate::Suffix;
+use std::io::{Read, Seek, Write};
 
-use anyhow::{anyhow, Result};
+use crate::{ParseOptions, Result, Suffix};
+
+use anyhow::anyhow;
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
 
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Check if the file is a DfuSe file.
-pub fn detect(file: &mut std::fs::File) -> Result<bool> {
+///
+/// Files with the DfuSe prefix signature but a suffix that is invalid or
+/// does not declare the DfuSe version are detected as
+/// [`Detection::InvalidSuffix`] rather than plain files.
+pub fn detect<R: Read + Seek>(file: &mut R) -> Result<Detection> {
     file.rewind()?;
     let mut signature = [0; 5];
     file.read_exact(&mut signature)?;
 
-    let suffix = Suffix::from_file(file)?;
+    if &signature != b"DfuSe" {
+        return Ok(Detection::NotDfuSe);
+    }
 
-    Ok(&signature == b"DfuSe" && suffix.bcdDFU == 0x011A)
+    file.seek(std::io::SeekFrom::End(-(Suffix::LENGTH as i64)))?;
+    let mut buffer = [0; Suffix::LENGTH];
+    file.read_exact(&mut buffer)?;
+
+    Ok(Detection::from_suffix(&Suffix::from_bytes(&buffer)))
+}
+
+/// Check if the file is a DfuSe file asynchronously.
+#[cfg(feature = "tokio")]
+pub async fn detect_async<R: AsyncRead + AsyncSeek + Unpin>(file: &mut R) -> Result<Detection> {
+    file.seek(std::io::SeekFrom::Start(0)).await?;
+    let mut signature = [0; 5];
+    file.read_exact(&mut signature).await?;
+
+    if &signature != b"DfuSe" {
+        return Ok(Detection::NotDfuSe);
+    }
+
+    file.seek(std::io::SeekFrom::End(-(Suffix::LENGTH as i64)))
+        .await?;
+    let mut buffer = [0; Suffix::LENGTH];
+    file.read_exact(&mut buffer).await?;
+
+    Ok(Detection::from_suffix(&Suffix::from_bytes(&buffer)))
+}
+
+/// Result of the DfuSe file detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    /// DfuSe prefix and valid suffix.
+    DfuSe,
+
+    /// DfuSe prefix, but the suffix signature is invalid or `bcdDFU`
+    /// is not 0x011A.
+    InvalidSuffix,
+
+    /// No DfuSe prefix.
+    NotDfuSe,
+}
+
+impl Detection {
+    /// Classifies a file with DfuSe prefix by its suffix.
+    fn from_suffix(suffix: &Suffix) -> Self {
+        if suffix.is_signature_valid() && suffix.bcdDFU == 0x011A {
+            Self::DfuSe
+        } else {
+            Self::InvalidSuffix
+        }
+    }
+
+    /// Returns if the file is a DfuSe file or an error if its suffix is invalid.
+    pub(crate) fn is_dfuse(self) -> Result<bool> {
+        match self {
+            Self::DfuSe => Ok(true),
+            Self::InvalidSuffix => Err(anyhow!(Error::InvalidSuffix)),
+            Self::NotDfuSe => Ok(false),
+        }
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Reference to the file content.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Content {
     /// The prefix header with metadata.
     pub prefix: Prefix,
@@ -39,37 +108,700 @@ impl Content {
         Self { prefix, images }
     }
 
+    /// Creates a new instance without any images.
+    ///
+    /// The prefix declares zero targets and the image size of the prefix
+    /// alone, so the content is valid and images can be added with
+    /// [`Content::push_image`].
+    pub fn empty() -> Self {
+        let prefix = Prefix {
+            DFUImageSize: Prefix::LENGTH as u32,
+            ..Default::default()
+        };
+
+        Self::new(prefix, Vec::new())
+    }
+
     /// Creates a new instance with data read from file.
-    pub fn from_file(file: &mut std::fs::File) -> Result<Self> {
+    pub fn from_file<R: Read + Seek>(file: &mut R) -> Result<Self> {
+        Self::from_file_with_options(file, &ParseOptions::default())
+    }
+
+    /// Creates a new instance by reading only the headers from file.
+    ///
+    /// Element payloads are never read, which makes this suitable for
+    /// extracting metadata from a large number of files. After reading an
+    /// element header, its data is skipped with a single seek to the next
+    /// header, so the amount of I/O depends only on the number of images and
+    /// elements, not on the size of the file. The position of each payload is
+    /// kept in [`ImageElement::data_position`] for reading it later.
+    pub fn parse_headers_only<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        Ok(Self::parse(reader, &ParseOptions::default(), false, &mut |_| {})?.0)
+    }
+
+    /// Creates a new instance with data read from file using custom parse options.
+    pub fn from_file_with_options<R: Read + Seek>(
+        file: &mut R,
+        options: &ParseOptions,
+    ) -> Result<Self> {
+        Ok(Self::parse(file, options, false, &mut |_| {})?.0)
+    }
+
+    /// Creates a new instance with data read from file, reporting the progress.
+    ///
+    /// The `progress` callback is invoked with a [`ParseEvent`] after each
+    /// target prefix and each image element is parsed.
+    pub fn from_file_with_progress<R: Read + Seek, F: FnMut(ParseEvent)>(
+        file: &mut R,
+        progress: &mut F,
+    ) -> Result<Self> {
+        Ok(Self::parse(file, &ParseOptions::default(), false, progress)?.0)
+    }
+
+    /// Creates a new instance with data read from file, keeping the images
+    /// parsed successfully before an error occurs.
+    ///
+    /// Parsing stops at the first image that fails to read. In that case,
+    /// the error is returned along with the content as a warning.
+    /// The prefix is kept as read, so `bTargets` may be larger than the
+    /// number of images returned.
+    pub fn from_file_lenient<R: Read + Seek>(
+        file: &mut R,
+    ) -> Result<(Self, Option<anyhow::Error>)> {
+        Self::parse(file, &ParseOptions::default(), true, &mut |_| {})
+    }
+
+    /// Parse the content from file, reporting the progress.
+    ///
+    /// If `lenient` is set, parsing stops at the first image that fails to
+    /// read and the error is returned along with the content.
+    pub(crate) fn parse<R: Read + Seek>(
+        file: &mut R,
+        options: &ParseOptions,
+        lenient: bool,
+        progress: &mut dyn FnMut(ParseEvent),
+    ) -> Result<(Self, Option<anyhow::Error>)> {
         let file_size = file.seek(std::io::SeekFrom::End(0))?;
+        Self::check_file_size(file_size)?;
 
-        // File must be at least as large as the prefix + standard suffix
-        if file_size < (PREFIX_LENGTH + 16) as u64 {
-            return Err(anyhow!(Error::InsufficientFileSize));
+        let prefix = Prefix::from_file(file)?;
+        prefix.check_version(options)?;
+
+        let mut images = Vec::new();
+        let mut warning = None;
+
+        let mut file_pos = Prefix::LENGTH as u64;
+        let mut total_size = 0;
+
+        for index in 0..prefix.bTargets as usize {
+            let result = Image::parse(file, &mut file_pos, index, progress).and_then(|image| {
+                Self::check_image(&image, index, options, &mut total_size)?;
+                Ok(image)
+            });
+
+            if !Self::collect_image(result, lenient, &mut images, &mut warning)? {
+                break;
+            }
         }
 
-        let prefix = Prefix::from_file(file)?;
+        if options.allow_short_last_element {
+            // bLength is the 12th byte of the suffix at the end of the file
+            file.seek(std::io::SeekFrom::End(-5))?;
+            let mut suffix_length = [0; 1];
+            file.read_exact(&mut suffix_length)?;
+
+            let data_end = Self::data_end(file_size, suffix_length[0]);
+            Self::clamp_last_element(&mut images, data_end, progress);
+        }
+
+        Ok((Self::new(prefix, images), warning))
+    }
+
+    /// Creates a new instance with data read from file asynchronously.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async<R: AsyncRead + AsyncSeek + Unpin>(file: &mut R) -> Result<Self> {
+        Self::from_file_async_with_options(file, &ParseOptions::default()).await
+    }
+
+    /// Creates a new instance with data read from file asynchronously
+    /// using custom parse options.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async_with_options<R: AsyncRead + AsyncSeek + Unpin>(
+        file: &mut R,
+        options: &ParseOptions,
+    ) -> Result<Self> {
+        Ok(Self::parse_async(file, options, false, &mut |_| {})
+            .await?
+            .0)
+    }
+
+    /// Parse the content from file asynchronously, reporting the progress.
+    ///
+    /// This is the counterpart of [`Content::parse`] and applies the same
+    /// checks, so both must only differ in the way the file is read.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn parse_async<R: AsyncRead + AsyncSeek + Unpin>(
+        file: &mut R,
+        options: &ParseOptions,
+        lenient: bool,
+        progress: &mut (dyn FnMut(ParseEvent) + Send),
+    ) -> Result<(Self, Option<anyhow::Error>)> {
+        let file_size = file.seek(std::io::SeekFrom::End(0)).await?;
+        Self::check_file_size(file_size)?;
+
+        let prefix = Prefix::from_file_async(file).await?;
+        prefix.check_version(options)?;
+
         let mut images = Vec::new();
+        let mut warning = None;
+
+        let mut file_pos = Prefix::LENGTH as u64;
+        let mut total_size = 0;
+
+        for index in 0..prefix.bTargets as usize {
+            let result = match Image::parse_async(file, &mut file_pos, index, progress).await {
+                Ok(image) => {
+                    Self::check_image(&image, index, options, &mut total_size).map(|_| image)
+                }
+                Err(error) => Err(error),
+            };
+
+            if !Self::collect_image(result, lenient, &mut images, &mut warning)? {
+                break;
+            }
+        }
+
+        if options.allow_short_last_element {
+            // bLength is the 12th byte of the suffix at the end of the file
+            file.seek(std::io::SeekFrom::End(-5)).await?;
+            let mut suffix_length = [0; 1];
+            file.read_exact(&mut suffix_length).await?;
+
+            let data_end = Self::data_end(file_size, suffix_length[0]);
+            Self::clamp_last_element(&mut images, data_end, progress);
+        }
+
+        Ok((Self::new(prefix, images), warning))
+    }
+
+    /// Check if the file is at least as large as the prefix and the
+    /// standard suffix.
+    fn check_file_size(file_size: u64) -> Result<()> {
+        if file_size < (Prefix::LENGTH + Suffix::LENGTH) as u64 {
+            return Err(anyhow!(Error::InsufficientFileSize));
+        }
+
+        Ok(())
+    }
+
+    /// Check a parsed image against the limits of the parse options.
+    ///
+    /// The `total_size` of all elements parsed so far is updated.
+    fn check_image(
+        image: &Image,
+        index: usize,
+        options: &ParseOptions,
+        total_size: &mut u64,
+    ) -> Result<()> {
+        for element in image.image_elements.iter() {
+            let size = element.dwElementSize;
+
+            if let Some(limit) = options.max_element_size.filter(|limit| size > *limit) {
+                return Err(anyhow!(Error::SizeLimitExceeded {
+                    declared: size as u64,
+                    limit: limit as u64,
+                }));
+            }
 
-        let mut file_pos = PREFIX_LENGTH as u64;
+            *total_size += size as u64;
+
+            if let Some(limit) = options.max_total_size.filter(|limit| *total_size > *limit) {
+                return Err(anyhow!(Error::SizeLimitExceeded {
+                    declared: *total_size,
+                    limit,
+                }));
+            }
+        }
+
+        let empty_element = image
+            .image_elements
+            .iter()
+            .position(|element| element.dwElementSize == 0);
+
+        match empty_element {
+            Some(element_index) if options.reject_empty_elements => {
+                Err(anyhow!(Error::EmptyElement {
+                    image: index,
+                    index: element_index,
+                }))
+            }
+            _ => Ok(()),
+        }
+    }
 
-        for _ in 0..prefix.bTargets {
-            let image = Image::from_file(file, &mut file_pos)?;
-            images.push(image);
+    /// Add the result of parsing an image to the images or the warning.
+    ///
+    /// Returns if parsing should continue. Errors are only kept as warning
+    /// if `lenient` is set, otherwise they are returned.
+    fn collect_image(
+        result: Result<Image>,
+        lenient: bool,
+        images: &mut Vec<Image>,
+        warning: &mut Option<anyhow::Error>,
+    ) -> Result<bool> {
+        match result {
+            Ok(image) => {
+                images.push(image);
+                Ok(true)
+            }
+            Err(error) if lenient => {
+                *warning = Some(error);
+                Ok(false)
+            }
+            Err(error) => Err(error),
         }
+    }
 
-        let content = Self::new(prefix, images);
+    /// Returns the position where the suffix of `bLength` bytes begins,
+    /// which is at least the standard suffix length.
+    fn data_end(file_size: u64, suffix_length: u8) -> u64 {
+        let suffix_length = (suffix_length as usize).max(Suffix::LENGTH);
+        file_size.saturating_sub(suffix_length as u64)
+    }
 
-        Ok(content)
+    /// Clamp the size of the last element to the data ending at `data_end`,
+    /// see [`ParseOptions::allow_short_last_element`].
+    fn clamp_last_element(
+        images: &mut [Image],
+        data_end: u64,
+        progress: &mut dyn FnMut(ParseEvent),
+    ) {
+        let last_element = images
+            .iter_mut()
+            .enumerate()
+            .last()
+            .and_then(|(target, image)| {
+                let element = image.image_elements.len().checked_sub(1)?;
+                Some((target, element, image.image_elements.last_mut()?))
+            });
+
+        if let Some((target, element_index, element)) = last_element {
+            let available = data_end.saturating_sub(element.data_position);
+
+            if (element.dwElementSize as u64) > available {
+                progress(ParseEvent::ElementClamped {
+                    target,
+                    element: element_index,
+                    declared_size: element.dwElementSize,
+                });
+                element.dwElementSize = available as u32;
+            }
+        }
+    }
+
+    /// Serialize the content into a sink.
+    ///
+    /// The prefix, target prefixes and element headers are written as they
+    /// are, so [`Content::recompute_sizes`] should be called after any
+    /// modifications. The data of each element is requested from the
+    /// `element_data` closure, which allows to take it from any source, e.g.
+    /// `|element| element.read_all(&mut file)`. The suffix is not written.
+    ///
+    /// Returns the number of bytes written.
+    pub fn write_to<W: Write, F: FnMut(&ImageElement) -> Result<Vec<u8>>>(
+        &self,
+        sink: &mut W,
+        mut element_data: F,
+    ) -> Result<u64> {
+        sink.write_all(&self.prefix.to_bytes())?;
+        let mut written = Prefix::LENGTH as u64;
+
+        for image in self.images.iter() {
+            sink.write_all(&image.target_prefix.to_bytes())?;
+            written += TargetPrefix::LENGTH as u64;
+
+            for element in image.image_elements.iter() {
+                let data = element_data(element)?;
+
+                if data.len() != element.dwElementSize as usize {
+                    return Err(anyhow!(Error::LengthMismatch {
+                        expected: element.dwElementSize as usize,
+                        actual: data.len(),
+                    }));
+                }
+
+                sink.write_all(&element.to_bytes())?;
+                sink.write_all(&data)?;
+                written += ImageElement::LENGTH as u64 + data.len() as u64;
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Append an image and update the number of targets.
+    ///
+    /// The image size in the prefix is not updated, call
+    /// [`Content::recompute_sizes`] after all modifications are done.
+    pub fn push_image(&mut self, image: Image) {
+        self.images.push(image);
+        self.prefix.bTargets = self.images.len() as u8;
+    }
+
+    /// Remove the first image with a specific alternate setting and
+    /// update the number of targets.
+    ///
+    /// Returns the removed image or `None` if no image was found.
+    pub fn remove_image_by_alt(&mut self, alt_setting: u8) -> Option<Image> {
+        let index = self
+            .images
+            .iter()
+            .position(|image| image.target_prefix.bAlternateSetting == alt_setting)?;
+        let image = self.images.remove(index);
+        self.prefix.bTargets = self.images.len() as u8;
+
+        Some(image)
+    }
+
+    /// Change the alternate setting of the image at `image_index`.
+    ///
+    /// The change is rejected if another image already uses `new_alt`.
+    pub fn set_alt_setting(&mut self, image_index: usize, new_alt: u8) -> Result<()> {
+        if self.images.iter().enumerate().any(|(index, image)| {
+            index != image_index && image.target_prefix.bAlternateSetting == new_alt
+        }) {
+            return Err(anyhow!(Error::DuplicateAltSetting(new_alt)));
+        }
+
+        let image = self
+            .images
+            .get_mut(image_index)
+            .ok_or_else(|| anyhow!(Error::InvalidImageIndex))?;
+        image.target_prefix.bAlternateSetting = new_alt;
+
+        Ok(())
+    }
+
+    /// Move the image at index `from` to index `to`, shifting the images
+    /// in between.
+    ///
+    /// Only the order of the images is changed, so the sizes stay valid.
+    /// Elements still refer to their data positions in the original file.
+    pub fn move_image(&mut self, from: usize, to: usize) -> Result<()> {
+        if from >= self.images.len() || to >= self.images.len() {
+            return Err(anyhow!(Error::InvalidImageIndex));
+        }
+
+        let image = self.images.remove(from);
+        self.images.insert(to, image);
+
+        Ok(())
+    }
+
+    /// Sort the images by their alternate setting.
+    ///
+    /// Images with equal alternate settings keep their order.
+    pub fn sort_images_by_alt(&mut self) {
+        self.images
+            .sort_by_key(|image| image.target_prefix.bAlternateSetting);
+    }
+
+    /// Update all sizes and counts in the prefix and the target prefixes
+    /// according to the contained images and elements.
+    ///
+    /// Returns an error if the total size does not fit into the size fields.
+    pub fn recompute_sizes(&mut self) -> Result<()> {
+        for image in self.images.iter_mut() {
+            image.recompute_sizes()?;
+        }
+
+        let image_size = Prefix::LENGTH as u64
+            + self
+                .images
+                .iter()
+                .map(|image| TargetPrefix::LENGTH as u64 + image.target_prefix.dwTargetSize as u64)
+                .sum::<u64>();
+
+        self.prefix.DFUImageSize =
+            u32::try_from(image_size).map_err(|_| anyhow!(Error::ImageTooLarge))?;
+        self.prefix.bTargets = self.images.len() as u8;
+
+        Ok(())
+    }
+
+    /// Split all elements larger than `transfer_size` bytes, so each element
+    /// can be downloaded in whole transfer blocks.
+    ///
+    /// This applies [`Image::split_element`] to every oversized element of
+    /// all images, so their data must be held in memory. This is checked for
+    /// all elements in advance, so the content is left unchanged on error.
+    /// The sizes in the prefix are updated afterwards.
+    pub fn rechunk_for_transfer(&mut self, transfer_size: u32) -> Result<()> {
+        if transfer_size == 0 {
+            return Err(anyhow!(Error::InvalidMaxSize));
+        }
+
+        let data_missing = self.images.iter().any(|image| {
+            image
+                .image_elements
+                .iter()
+                .any(|element| element.dwElementSize > transfer_size && element.data.is_none())
+        });
+
+        if data_missing {
+            return Err(anyhow!(Error::ElementDataNotLoaded));
+        }
+
+        for image in self.images.iter_mut() {
+            // Iterate backwards, so splitting does not shift pending indices
+            for index in (0..image.image_elements.len()).rev() {
+                image.split_element(index, transfer_size)?;
+            }
+        }
+
+        self.recompute_sizes()
     }
 
     /// Find an image with a specific alternate setting.
+    ///
+    /// Only the first match is returned, use
+    /// [`Content::find_duplicate_alt_settings`] to detect ambiguous files.
     pub fn find_image_by_alt(&self, alt_setting: u8) -> Option<&Image> {
         self.images
             .iter()
             .find(|&image| image.target_prefix.bAlternateSetting == alt_setting)
     }
 
+    /// Returns the sum of the sizes of all image elements in bytes.
+    pub fn total_element_bytes(&self) -> u64 {
+        self.images.iter().map(Image::total_element_bytes).sum()
+    }
+
+    /// Returns the lowest element address and the address following the
+    /// end of the highest element across all images, or `None` if there
+    /// are no elements.
+    ///
+    /// See [`Image::max_address`] for the end address.
+    pub fn address_span(&self) -> Option<(u32, u32)> {
+        let min = self
+            .images
+            .iter()
+            .flat_map(|image| image.image_elements.iter())
+            .map(|element| element.dwElementAddress)
+            .min()?;
+        let max = self.images.iter().filter_map(Image::max_address).max()?;
+
+        Some((min, max))
+    }
+
+    /// Returns the number of image elements in all images.
+    pub fn element_count(&self) -> usize {
+        self.images
+            .iter()
+            .map(|image| image.image_elements.len())
+            .sum()
+    }
+
+    /// Returns the location of all image elements as tuples of
+    /// alternate setting, element address, file offset of the data
+    /// and data size.
+    ///
+    /// The file offsets are only meaningful for elements read from a file.
+    pub fn element_offsets(&self) -> Vec<(u8, u32, u64, u32)> {
+        self.images
+            .iter()
+            .flat_map(|image| {
+                image.image_elements.iter().map(move |element| {
+                    (
+                        image.target_prefix.bAlternateSetting,
+                        element.dwElementAddress,
+                        element.data_position,
+                        element.dwElementSize,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over all image elements, yielding the metadata
+    /// and the complete data of each element.
+    ///
+    /// The data is read lazily when advancing the iterator. As the file is
+    /// borrowed mutably, the iterator must be dropped before other file
+    /// operations.
+    pub fn read_elements<'a, R: Read + Seek>(
+        &'a self,
+        file: &'a mut R,
+    ) -> impl Iterator<Item = Result<(ElementMeta, Vec<u8>)>> + 'a {
+        self.images
+            .iter()
+            .enumerate()
+            .flat_map(|(image_index, image)| {
+                image
+                    .image_elements
+                    .iter()
+                    .enumerate()
+                    .map(move |(element_index, element)| {
+                        let meta = ElementMeta {
+                            image_index,
+                            element_index,
+                            alt_setting: image.target_prefix.bAlternateSetting,
+                            address: element.dwElementAddress,
+                            size: element.dwElementSize,
+                        };
+
+                        (meta, element)
+                    })
+            })
+            .map(move |(meta, element)| Ok((meta, element.read_all(file)?)))
+    }
+
+    /// Returns an iterator over all image elements, checking each element
+    /// when advancing the iterator.
+    ///
+    /// Elements with a size of zero or with data extending beyond the end of
+    /// the file are yielded as errors, so valid elements can be processed and
+    /// errors collected in a single pass.
+    pub fn validated_elements<'a, R: Read + Seek>(
+        &'a self,
+        file: &'a mut R,
+    ) -> impl Iterator<Item = Result<&'a ImageElement>> + 'a {
+        self.images
+            .iter()
+            .enumerate()
+            .flat_map(|(image_index, image)| {
+                image
+                    .image_elements
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, element)| (image_index, index, element))
+            })
+            .map(move |(image, index, element)| {
+                if element.dwElementSize == 0 {
+                    return Err(anyhow!(Error::EmptyElement { image, index }));
+                }
+
+                if !element.verify_data_present(file)? {
+                    return Err(anyhow!(Error::ElementDataMissing { image, index }));
+                }
+
+                Ok(element)
+            })
+    }
+
+    /// Returns the number of bytes between the end of the last image element
+    /// and the start of the suffix for a file of `file_size` bytes.
+    ///
+    /// A nonzero value indicates additional data hidden in the file.
+    /// The standard suffix length is assumed, so for extended suffixes,
+    /// the length of the vendor data must be subtracted from `file_size`.
+    pub fn trailing_gap(&self, file_size: u64) -> u64 {
+        file_size.saturating_sub(self.computed_file_size())
+    }
+
+    /// Returns the file size computed from the headers, including
+    /// a standard suffix.
+    ///
+    /// Note that `DFUImageSize` in the prefix excludes the suffix,
+    /// so it should hold this value minus [`Suffix::LENGTH`].
+    pub fn computed_file_size(&self) -> u64 {
+        self.images_end() + Suffix::LENGTH as u64
+    }
+
+    /// Returns the offset of the end of the last image element, which is
+    /// the image size computed from the elements.
+    pub(crate) fn images_end(&self) -> u64 {
+        Prefix::LENGTH as u64
+            + self
+                .images
+                .iter()
+                .map(|image| TargetPrefix::LENGTH as u64 + image.elements_size())
+                .sum::<u64>()
+    }
+
+    /// Returns the alternate settings used by more than one image.
+    pub fn find_duplicate_alt_settings(&self) -> Vec<u8> {
+        let mut duplicates = Vec::new();
+
+        for (index, image) in self.images.iter().enumerate() {
+            let alt_setting = image.target_prefix.bAlternateSetting;
+
+            if !duplicates.contains(&alt_setting)
+                && self.images[index + 1..]
+                    .iter()
+                    .any(|other| other.target_prefix.bAlternateSetting == alt_setting)
+            {
+                duplicates.push(alt_setting);
+            }
+        }
+
+        duplicates
+    }
+
+    /// Check that the address ranges of all elements lie completely inside
+    /// a region of the memory layout.
+    ///
+    /// The error names the first element found outside the layout.
+    pub fn validate_against_layout(&self, layout: &layout::MemoryLayout) -> Result<()> {
+        for image in self.images.iter() {
+            for (index, element) in image.image_elements.iter().enumerate() {
+                if layout
+                    .find_region(element.dwElementAddress, element.dwElementSize)
+                    .is_none()
+                {
+                    return Err(anyhow!(Error::ElementOutsideLayout {
+                        alt_setting: image.target_prefix.bAlternateSetting,
+                        index,
+                        address: element.dwElementAddress,
+                    }));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns all elements with a start address that is not a multiple
+    /// of `alignment`.
+    pub fn check_alignment(&self, alignment: u32) -> Result<Vec<ElementMeta>> {
+        if alignment == 0 {
+            return Err(anyhow!(Error::InvalidAlignment));
+        }
+
+        let mut misaligned = Vec::new();
+
+        for (image_index, image) in self.images.iter().enumerate() {
+            for (element_index, element) in image.image_elements.iter().enumerate() {
+                if !element.is_aligned(alignment) {
+                    misaligned.push(ElementMeta {
+                        image_index,
+                        element_index,
+                        alt_setting: image.target_prefix.bAlternateSetting,
+                        address: element.dwElementAddress,
+                        size: element.dwElementSize,
+                    });
+                }
+            }
+        }
+
+        Ok(misaligned)
+    }
+
+    /// Find the image with elements inside the address span of a region
+    /// of a memory layout.
+    ///
+    /// An image matches if it has at least one element and all its elements
+    /// lie completely inside the region.
+    pub fn image_for_region(&self, region: &layout::MemoryRegion) -> Option<&Image> {
+        self.images.iter().find(|image| {
+            !image.image_elements.is_empty()
+                && image
+                    .image_elements
+                    .iter()
+                    .all(|element| region.contains(element.dwElementAddress, element.dwElementSize))
+        })
+    }
+
     /// Find an image with a specific name.
     pub fn find_image_by_name<T: AsRef<str>>(&self, name: T) -> Option<&Image> {
         self.images
@@ -80,8 +812,89 @@ impl Content {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Metadata of an image element, see [`Content::read_elements`]
+/// and [`Content::check_alignment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElementMeta {
+    /// Index of the image containing the element.
+    pub image_index: usize,
+
+    /// Index of the element inside the image.
+    pub element_index: usize,
+
+    /// Alternate setting of the image.
+    pub alt_setting: u8,
+
+    /// Start address of the element.
+    pub address: u32,
+
+    /// Size of the element data in bytes.
+    pub size: u32,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Key identifying an image element by its address and size,
+/// see [`ImageElement::key`].
+///
+/// Unlike [`ImageElement`] itself, the key excludes the data position,
+/// which is a file offset, and the in-memory data. It can be used to
+/// deduplicate elements, e.g. as the key of a `HashSet` or `HashMap`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ElementKey {
+    /// Start address of the element.
+    pub address: u32,
+
+    /// Size of the element data in bytes.
+    pub size: u32,
+}
+
+impl ElementKey {
+    /// Creates a new element key.
+    pub fn new(address: u32, size: u32) -> Self {
+        Self { address, size }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Progress events reported while parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseEvent {
+    /// The target prefix with the given index was parsed.
+    TargetParsed(usize),
+
+    /// An image element header was parsed.
+    ElementParsed {
+        /// Index of the target containing the element.
+        target: usize,
+
+        /// Index of the element inside the target.
+        element: usize,
+
+        /// Position inside the file after the element data.
+        file_pos: u64,
+    },
+
+    /// The size of the last element was clamped to the end of the file,
+    /// see [`ParseOptions::allow_short_last_element`].
+    ElementClamped {
+        /// Index of the target containing the element.
+        target: usize,
+
+        /// Index of the element inside the target.
+        element: usize,
+
+        /// Element size declared in the file.
+        declared_size: u32,
+    },
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 /// Length of the file prefix in bytes.
-pub const PREFIX_LENGTH: usize = 11;
+#[deprecated(since = "0.3.0", note = "use `Prefix::LENGTH` instead")]
+pub const PREFIX_LENGTH: usize = Prefix::LENGTH;
 
 /// File prefix, see UM0391 section 2.1.
 ///
@@ -89,7 +902,7 @@ pub const PREFIX_LENGTH: usize = 11;
 /// software application, used to retrieve the file context,
 /// and enable valid DFU files to be recognized.
 #[allow(non_snake_case)]
-#[derive(Debug, Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Prefix {
     /// File identifier, must contain "DfuSe".
     pub szSignature: String,
@@ -97,13 +910,26 @@ pub struct Prefix {
     /// Format revision, usually 0x01.
     pub bVersion: u8,
 
-    /// Total file length in bytes (including the suffix).
+    /// Total length of the prefix, the target prefixes and the image elements
+    /// in bytes, i.e. the file length excluding the suffix.
     pub DFUImageSize: u32,
 
     /// Number of images stored in the file.
     pub bTargets: u8,
 }
 
+impl std::fmt::Debug for Prefix {
+    /// Formats the prefix with the image size in hexadecimal notation.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Prefix")
+            .field("szSignature", &self.szSignature)
+            .field("bVersion", &self.bVersion)
+            .field("DFUImageSize", &format_args!("0x{:X}", self.DFUImageSize))
+            .field("bTargets", &self.bTargets)
+            .finish()
+    }
+}
+
 impl Default for Prefix {
     /// Creates a new prefix with default values.
     fn default() -> Self {
@@ -117,6 +943,9 @@ impl Default for Prefix {
 }
 
 impl Prefix {
+    /// Length of the file prefix in bytes.
+    pub const LENGTH: usize = 11;
+
     /// Creates a new prefix.
     pub fn new(signature: String, version: u8, image_size: u32, num_targets: u8) -> Self {
         Self {
@@ -127,30 +956,105 @@ impl Prefix {
         }
     }
 
+    /// Returns the format version.
+    pub fn version(&self) -> u8 {
+        self.bVersion
+    }
+
+    /// Returns the declared image size in bytes, excluding the suffix.
+    pub fn image_size(&self) -> u32 {
+        self.DFUImageSize
+    }
+
+    /// Returns the declared number of targets.
+    pub fn target_count(&self) -> u8 {
+        self.bTargets
+    }
+
     /// Creates a new prefix from a buffer of u8 values.
-    pub fn from_bytes(buffer: &[u8; PREFIX_LENGTH]) -> Self {
+    pub fn from_bytes(buffer: &[u8; Prefix::LENGTH]) -> Self {
         Self::new(
             String::from_utf8_lossy(&buffer[0..5]).to_string(),
-            u8::from_le(buffer[5]),
+            buffer[5],
             u32::from_le_bytes([buffer[6], buffer[7], buffer[8], buffer[9]]),
-            u8::from_le(buffer[10]),
+            buffer[10],
         )
     }
 
+    /// Returns the prefix as bytes in file order.
+    pub fn to_bytes(&self) -> [u8; Prefix::LENGTH] {
+        let mut buffer = [0; Prefix::LENGTH];
+
+        for (dest, src) in buffer[0..5].iter_mut().zip(self.szSignature.as_bytes()) {
+            *dest = *src;
+        }
+
+        buffer[5] = self.bVersion;
+        buffer[6..10].copy_from_slice(&self.DFUImageSize.to_le_bytes());
+        buffer[10] = self.bTargets;
+
+        buffer
+    }
+
+    /// Check if the format revision is supported, unless other versions
+    /// are explicitly allowed by the options.
+    fn check_version(&self, options: &ParseOptions) -> Result<()> {
+        if self.bVersion != 1 && !options.allow_unsupported_version {
+            return Err(anyhow!(Error::UnsupportedDfuSeVersion(self.bVersion)));
+        }
+
+        Ok(())
+    }
+
     /// Creates a new prefix from reading a file.
-    pub fn from_file(file: &mut std::fs::File) -> Result<Self> {
+    pub fn from_file<R: Read + Seek>(file: &mut R) -> Result<Self> {
         file.rewind()?;
-        let mut buffer = [0; PREFIX_LENGTH];
+        let mut buffer = [0; Prefix::LENGTH];
         file.read_exact(&mut buffer)?;
 
+        Self::check_signature(&buffer)?;
         let data = Self::from_bytes(&buffer);
 
-        if &data.szSignature != "DfuSe" {
-            return Err(anyhow!(Error::InvalidPrefixSignature));
-        }
+        Ok(data)
+    }
+
+    /// Creates a new prefix from reading a file asynchronously.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async<R: AsyncRead + AsyncSeek + Unpin>(file: &mut R) -> Result<Self> {
+        file.seek(std::io::SeekFrom::Start(0)).await?;
+        let mut buffer = [0; Prefix::LENGTH];
+        file.read_exact(&mut buffer).await?;
+
+        Self::check_signature(&buffer)?;
+        let data = Self::from_bytes(&buffer);
 
         Ok(data)
     }
+
+    /// Check the raw signature bytes of a prefix buffer.
+    fn check_signature(buffer: &[u8; Prefix::LENGTH]) -> Result<()> {
+        if &buffer[0..5] != b"DfuSe" {
+            let mut found = [0; 5];
+            found.copy_from_slice(&buffer[0..5]);
+            return Err(anyhow!(Error::InvalidPrefixSignature { found }));
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<&[u8]> for Prefix {
+    type Error = Error;
+
+    /// Creates a new prefix from a slice, which must have the prefix length.
+    fn try_from(buffer: &[u8]) -> Result<Self, Self::Error> {
+        let buffer = buffer.try_into().map_err(|_| Error::LengthMismatch {
+            expected: Prefix::LENGTH,
+            actual: buffer.len(),
+        })?;
+
+        Ok(Self::from_bytes(buffer))
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -159,7 +1063,7 @@ impl Prefix {
 ///
 /// The DFU Image contains the effective data of the firmware,
 /// starting by a Target prefix record followed by a number of Image elements
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Image {
     /// Target prefix record containing metadata.
     pub target_prefix: TargetPrefix,
@@ -191,31 +1095,465 @@ impl Image {
     ///
     /// The `file_pos` argument must be set to the postion inside the file as
     /// offset from the start and is updated according to the number of bytes read.
-    pub fn from_file(file: &mut std::fs::File, file_pos: &mut u64) -> Result<Self> {
+    pub fn from_file<R: Read + Seek>(file: &mut R, file_pos: &mut u64) -> Result<Self> {
+        Self::parse(file, file_pos, 0, &mut |_| {})
+    }
+
+    /// Parse an image from file, reporting the progress for the target `index`.
+    fn parse<R: Read + Seek>(
+        file: &mut R,
+        file_pos: &mut u64,
+        index: usize,
+        progress: &mut dyn FnMut(ParseEvent),
+    ) -> Result<Self> {
         let target_prefix = TargetPrefix::from_file(file, file_pos)?;
+        progress(ParseEvent::TargetParsed(index));
+
         let mut image_elements = Vec::new();
 
-        for _ in 0..target_prefix.dwNbElements {
+        for element_index in 0..target_prefix.dwNbElements as usize {
             let image_element = ImageElement::from_file(file, file_pos)?;
             image_elements.push(image_element);
+            progress(ParseEvent::ElementParsed {
+                target: index,
+                element: element_index,
+                file_pos: *file_pos,
+            });
+        }
+
+        let image = Image::new(target_prefix, image_elements);
+
+        Ok(image)
+    }
+
+    /// Creates a new image by reading a file asynchronously.
+    ///
+    /// The `file_pos` argument must be set to the postion inside the file as
+    /// offset from the start and is updated according to the number of bytes read.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async<R: AsyncRead + AsyncSeek + Unpin>(
+        file: &mut R,
+        file_pos: &mut u64,
+    ) -> Result<Self> {
+        Self::parse_async(file, file_pos, 0, &mut |_| {}).await
+    }
+
+    /// Parse an image from file asynchronously, reporting the progress
+    /// for the target `index`.
+    #[cfg(feature = "tokio")]
+    async fn parse_async<R: AsyncRead + AsyncSeek + Unpin>(
+        file: &mut R,
+        file_pos: &mut u64,
+        index: usize,
+        progress: &mut (dyn FnMut(ParseEvent) + Send),
+    ) -> Result<Self> {
+        let target_prefix = TargetPrefix::from_file_async(file, file_pos).await?;
+        progress(ParseEvent::TargetParsed(index));
+
+        let mut image_elements = Vec::new();
+
+        for element_index in 0..target_prefix.dwNbElements as usize {
+            let image_element = ImageElement::from_file_async(file, file_pos).await?;
+            image_elements.push(image_element);
+            progress(ParseEvent::ElementParsed {
+                target: index,
+                element: element_index,
+                file_pos: *file_pos,
+            });
         }
 
         let image = Image::new(target_prefix, image_elements);
 
-        Ok(image)
+        Ok(image)
+    }
+
+    /// Returns the size of all elements including their headers,
+    /// which is the target size computed from the elements.
+    pub(crate) fn elements_size(&self) -> u64 {
+        self.image_elements
+            .iter()
+            .map(|element| ImageElement::LENGTH as u64 + element.dwElementSize as u64)
+            .sum()
+    }
+
+    /// Check if the image targets the option bytes instead of the flash memory.
+    ///
+    /// The detection is based on the target name, which follows the memory
+    /// layout naming convention, e.g. `@Option Bytes  /0x1FFFF800/01*016 e`.
+    pub fn is_option_bytes(&self) -> bool {
+        self.target_prefix
+            .szTargetName
+            .trim_start_matches('@')
+            .trim_start()
+            .to_ascii_lowercase()
+            .starts_with("option bytes")
+    }
+
+    /// Returns the sum of the sizes of all image elements in bytes.
+    pub fn total_element_bytes(&self) -> u64 {
+        self.image_elements
+            .iter()
+            .map(|element| element.dwElementSize as u64)
+            .sum()
+    }
+
+    /// Returns the address following the end of the highest element
+    /// or `None` if the image has no elements.
+    ///
+    /// The result saturates at `u32::MAX` for elements reaching the end
+    /// of the address space.
+    pub fn max_address(&self) -> Option<u32> {
+        self.image_elements
+            .iter()
+            .map(|element| element.dwElementAddress as u64 + element.dwElementSize as u64)
+            .max()
+            .map(|end| std::cmp::min(end, u32::MAX as u64) as u32)
+    }
+
+    /// Check if all elements are located inside the flash memory of
+    /// `flash_size` bytes starting at `flash_base`.
+    ///
+    /// An image without elements always fits.
+    pub fn fits_in(&self, flash_base: u32, flash_size: u32) -> bool {
+        let flash_end = flash_base as u64 + flash_size as u64;
+
+        self.image_elements.iter().all(|element| {
+            let start = element.dwElementAddress as u64;
+            start >= flash_base as u64 && start + element.dwElementSize as u64 <= flash_end
+        })
+    }
+
+    /// Returns the image elements sorted by their address.
+    ///
+    /// Elements with equal addresses keep their order from the file.
+    pub fn elements_sorted_by_address(&self) -> Vec<&ImageElement> {
+        let mut elements = self.image_elements.iter().collect::<Vec<_>>();
+        elements.sort_by_key(|element| element.dwElementAddress);
+
+        elements
+    }
+
+    /// Returns the index pairs of all elements with overlapping address ranges.
+    pub fn find_overlaps(&self) -> Vec<(usize, usize)> {
+        let mut overlaps = Vec::new();
+
+        for (index, element) in self.image_elements.iter().enumerate() {
+            let start = element.dwElementAddress as u64;
+            let end = start + element.dwElementSize as u64;
+
+            for (other_index, other) in self.image_elements.iter().enumerate().skip(index + 1) {
+                let other_start = other.dwElementAddress as u64;
+                let other_end = other_start + other.dwElementSize as u64;
+
+                if start < other_end && other_start < end {
+                    overlaps.push((index, other_index));
+                }
+            }
+        }
+
+        overlaps
+    }
+
+    /// Returns the address ranges between the elements that are not covered
+    /// by any element, as tuples of start address and end address.
+    ///
+    /// The end address is exclusive. Overlapping elements are merged before
+    /// computing the gaps, so every returned range is really unwritten.
+    pub fn address_gaps(&self) -> Vec<(u32, u32)> {
+        let mut gaps = Vec::new();
+        let mut covered_end = None;
+
+        for element in self.elements_sorted_by_address() {
+            let start = element.dwElementAddress as u64;
+            let end = start + element.dwElementSize as u64;
+
+            match covered_end {
+                Some(covered) if start > covered => {
+                    gaps.push((covered as u32, start as u32));
+                    covered_end = Some(end);
+                }
+                Some(covered) => covered_end = Some(std::cmp::max(covered, end)),
+                None => covered_end = Some(end),
+            }
+        }
+
+        gaps
+    }
+
+    /// Append a new element with data held in memory and update the
+    /// number of elements and the target size.
+    ///
+    /// The element is rejected if its address range overlaps with an
+    /// existing element, in which case the image is left unchanged.
+    pub fn append_element(&mut self, address: u32, data: Vec<u8>) -> Result<()> {
+        let new_element = ImageElement::with_data(address, data)?;
+        let start = address as u64;
+        let end = start + new_element.dwElementSize as u64;
+
+        let overlaps = self.image_elements.iter().any(|element| {
+            let element_start = element.dwElementAddress as u64;
+            start < element_start + element.dwElementSize as u64 && element_start < end
+        });
+
+        if overlaps {
+            return Err(anyhow!(Error::OverlappingElements));
+        }
+
+        self.image_elements.push(new_element);
+
+        if let Err(error) = self.recompute_sizes() {
+            self.image_elements.pop();
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Replace the data of the element at `index` with new data held in memory.
+    ///
+    /// The size of the element and the target size are updated accordingly.
+    /// If the new sizes do not fit into the size fields, an error is returned
+    /// and the image is left unchanged.
+    pub fn set_element_data(&mut self, index: usize, data: Vec<u8>) -> Result<()> {
+        let old_size = self
+            .image_elements
+            .get(index)
+            .ok_or_else(|| anyhow!(Error::InvalidElementIndex))?
+            .dwElementSize;
+        let size = u32::try_from(data.len()).map_err(|_| anyhow!(Error::ImageTooLarge))?;
+
+        let target_size = self.elements_size() - old_size as u64 + size as u64;
+        u32::try_from(target_size).map_err(|_| anyhow!(Error::ImageTooLarge))?;
+
+        let element = &mut self.image_elements[index];
+        element.dwElementSize = size;
+        element.data = Some(data);
+
+        self.recompute_sizes()
+    }
+
+    /// Pad the data of the element at `index` with the `fill` value,
+    /// so that its size becomes a multiple of `alignment`.
+    ///
+    /// The element data must be held in memory, see [`Image::load_data`].
+    /// The size of the element and the target size are updated accordingly.
+    pub fn pad_element_to(&mut self, index: usize, alignment: u32, fill: u8) -> Result<()> {
+        if alignment == 0 {
+            return Err(anyhow!(Error::InvalidAlignment));
+        }
+
+        let element = self
+            .image_elements
+            .get_mut(index)
+            .ok_or_else(|| anyhow!(Error::InvalidElementIndex))?;
+
+        let remainder = element.dwElementSize % alignment;
+
+        if remainder == 0 {
+            return Ok(());
+        }
+
+        let padded_size = element
+            .dwElementSize
+            .checked_add(alignment - remainder)
+            .ok_or_else(|| anyhow!(Error::ImageTooLarge))?;
+
+        let data = element
+            .data
+            .as_mut()
+            .ok_or_else(|| anyhow!(Error::ElementDataNotLoaded))?;
+        data.resize(padded_size as usize, fill);
+        element.dwElementSize = padded_size;
+
+        self.recompute_sizes()
+    }
+
+    /// Split the element at `index` into contiguous elements of at most
+    /// `max_size` bytes each.
+    ///
+    /// The element data must be held in memory, see [`Image::load_data`].
+    /// The new elements replace the original one at the same position,
+    /// so the represented memory contents are unchanged.
+    pub fn split_element(&mut self, index: usize, max_size: u32) -> Result<()> {
+        if max_size == 0 {
+            return Err(anyhow!(Error::InvalidMaxSize));
+        }
+
+        let element = self
+            .image_elements
+            .get(index)
+            .ok_or_else(|| anyhow!(Error::InvalidElementIndex))?;
+
+        if element.dwElementSize <= max_size {
+            return Ok(());
+        }
+
+        let data = element
+            .data
+            .as_ref()
+            .ok_or_else(|| anyhow!(Error::ElementDataNotLoaded))?;
+
+        let elements = data
+            .chunks(max_size as usize)
+            .enumerate()
+            .map(|(chunk_index, chunk)| {
+                let address = u32::try_from(chunk_index)
+                    .ok()
+                    .and_then(|chunk_index| chunk_index.checked_mul(max_size))
+                    .and_then(|offset| element.dwElementAddress.checked_add(offset))
+                    .ok_or_else(|| anyhow!(Error::AddressOverflow))?;
+
+                ImageElement::with_data(address, chunk.to_vec())
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.image_elements.splice(index..=index, elements);
+
+        self.recompute_sizes()
+    }
+
+    /// Merge consecutive elements with directly contiguous address ranges
+    /// into single elements.
+    ///
+    /// An element is merged into its predecessor if it starts exactly where
+    /// the predecessor ends. The element data must be held in memory for all
+    /// elements that are merged, see [`Image::load_data`].
+    pub fn coalesce_elements(&mut self) -> Result<()> {
+        let is_contiguous = |previous: &ImageElement, element: &ImageElement| {
+            previous.dwElementAddress as u64 + previous.dwElementSize as u64
+                == element.dwElementAddress as u64
+        };
+
+        // Check all merges in advance to leave the image untouched on error
+        let mut merged_size = 0;
+
+        for (index, element) in self.image_elements.iter().enumerate() {
+            let data_size = element.data.as_ref().map_or(0, |data| data.len() as u64);
+
+            match index
+                .checked_sub(1)
+                .map(|index| &self.image_elements[index])
+            {
+                Some(previous) if is_contiguous(previous, element) => {
+                    if previous.data.is_none() || element.data.is_none() {
+                        return Err(anyhow!(Error::ElementDataNotLoaded));
+                    }
+
+                    merged_size += data_size;
+
+                    if u32::try_from(merged_size).is_err() {
+                        return Err(anyhow!(Error::ImageTooLarge));
+                    }
+                }
+                _ => merged_size = data_size,
+            }
+        }
+
+        let mut elements: Vec<ImageElement> = Vec::with_capacity(self.image_elements.len());
+
+        for element in self.image_elements.drain(..) {
+            if let Some(previous) = elements.last_mut() {
+                if is_contiguous(previous, &element) {
+                    if let (Some(previous_data), Some(data)) =
+                        (previous.data.as_mut(), element.data)
+                    {
+                        previous_data.extend_from_slice(&data);
+                        previous.dwElementSize = u32::try_from(previous_data.len())
+                            .map_err(|_| anyhow!(Error::ImageTooLarge))?;
+                    }
+                    continue;
+                }
+            }
+
+            elements.push(element);
+        }
+
+        self.image_elements = elements;
+
+        self.recompute_sizes()
+    }
+
+    /// Read the data of all elements from the file into memory.
+    pub fn load_data<R: Read + Seek>(&mut self, file: &mut R) -> Result<()> {
+        for element in self.image_elements.iter_mut() {
+            element.load_data(file)?;
+        }
+
+        Ok(())
+    }
+
+    /// Convert the image into a flat binary.
+    ///
+    /// Each element is placed at its address relative to the lowest element
+    /// address, which is returned as base address along with the binary.
+    /// Gaps between the elements are filled with the `fill` value.
+    /// Overlapping elements result in an error.
+    pub fn to_flat_binary<R: Read + Seek>(&self, file: &mut R, fill: u8) -> Result<(u32, Vec<u8>)> {
+        let elements = self.elements_sorted_by_address();
+
+        for pair in elements.windows(2) {
+            let end_address = pair[0].dwElementAddress as u64 + pair[0].dwElementSize as u64;
+            if end_address > pair[1].dwElementAddress as u64 {
+                return Err(anyhow!(Error::OverlappingElements));
+            }
+        }
+
+        let (first, last) = match (elements.first(), elements.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok((0, Vec::new())),
+        };
+
+        let base_address = first.dwElementAddress;
+        let end_address = last.dwElementAddress as u64 + last.dwElementSize as u64;
+        let mut binary = vec![fill; (end_address - base_address as u64) as usize];
+
+        for element in elements {
+            let data = element.read_all(file)?;
+
+            if data.len() != element.dwElementSize as usize {
+                return Err(anyhow!(Error::LengthMismatch {
+                    expected: element.dwElementSize as usize,
+                    actual: data.len(),
+                }));
+            }
+
+            let offset = (element.dwElementAddress - base_address) as usize;
+            binary[offset..offset + data.len()].copy_from_slice(&data);
+        }
+
+        Ok((base_address, binary))
+    }
+
+    /// Update the number of elements and the target size in the target prefix
+    /// according to the image elements.
+    ///
+    /// Returns an error if the target size does not fit into the size field.
+    pub fn recompute_sizes(&mut self) -> Result<()> {
+        let target_size = self
+            .image_elements
+            .iter()
+            .map(|element| ImageElement::LENGTH as u64 + element.dwElementSize as u64)
+            .sum::<u64>();
+
+        self.target_prefix.dwTargetSize =
+            u32::try_from(target_size).map_err(|_| anyhow!(Error::ImageTooLarge))?;
+        self.target_prefix.dwNbElements = self.image_elements.len() as u32;
+
+        Ok(())
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Length of the target prefix in bytes.
-pub const TARGET_PREFIX_LENGTH: usize = 274;
+#[deprecated(since = "0.3.0", note = "use `TargetPrefix::LENGTH` instead")]
+pub const TARGET_PREFIX_LENGTH: usize = TargetPrefix::LENGTH;
 
 /// Target prefix of an image, see UM0391 section 2.3.2.
 ///
 /// The target prefix record is used to describe the associated image
 #[allow(non_snake_case)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TargetPrefix {
     /// Target identifier, must contain "Target".
     pub szSignature: String,
@@ -251,6 +1589,9 @@ impl Default for TargetPrefix {
 }
 
 impl TargetPrefix {
+    /// Length of the target prefix in bytes.
+    pub const LENGTH: usize = 274;
+
     /// Creates a new target prefix.
     pub fn new(
         signature: String,
@@ -271,58 +1612,227 @@ impl TargetPrefix {
     }
 
     /// Creates a new target prefix from a buffer of u8 values.
-    pub fn from_bytes(buffer: &[u8; TARGET_PREFIX_LENGTH]) -> Self {
-        // The target name in the buffer is a null-terminated C string
-        // but often the rest of the buffer contains garbage.
-        // So we do some extra work here to detect the real length used.
-        let target_name_full = String::from_utf8_lossy(&buffer[11..266]).to_string();
-        let target_name_len = target_name_full.find('\x00');
-
-        // If no null character is found, length is set to maximum of 255.
-        let target_name_len = target_name_len.unwrap_or(255);
-
+    pub fn from_bytes(buffer: &[u8; TargetPrefix::LENGTH]) -> Self {
         Self::new(
-            String::from_utf8_lossy(&buffer[0..6]).to_string(),
-            u8::from_le(buffer[6]),
-            u8::from_le(buffer[7]),
-            String::from_utf8_lossy(&buffer[11..266])[0..target_name_len].to_string(),
+            String::from_utf8_lossy(&buffer[0..6]).into_owned(),
+            buffer[6],
+            buffer[7],
+            String::from_utf8_lossy(&buffer[11..11 + Self::name_len(buffer)]).into_owned(),
             u32::from_le_bytes([buffer[266], buffer[267], buffer[268], buffer[269]]),
             u32::from_le_bytes([buffer[270], buffer[271], buffer[272], buffer[273]]),
         )
     }
 
+    /// Overwrites the target prefix with the content of a buffer of u8 values.
+    ///
+    /// In contrast to [`TargetPrefix::from_bytes`], the strings of the
+    /// existing target prefix are reused, so parsing many target prefixes
+    /// one after the other does not allocate once their capacity suffices.
+    pub fn update_from_bytes(&mut self, buffer: &[u8; TargetPrefix::LENGTH]) {
+        replace_lossy(&mut self.szSignature, &buffer[0..6]);
+        self.bAlternateSetting = buffer[6];
+        self.bTargetNamed = buffer[7];
+        replace_lossy(
+            &mut self.szTargetName,
+            &buffer[11..11 + Self::name_len(buffer)],
+        );
+        self.dwTargetSize =
+            u32::from_le_bytes([buffer[266], buffer[267], buffer[268], buffer[269]]);
+        self.dwNbElements =
+            u32::from_le_bytes([buffer[270], buffer[271], buffer[272], buffer[273]]);
+    }
+
+    /// Returns the length of the target name in a buffer of u8 values.
+    fn name_len(buffer: &[u8; TargetPrefix::LENGTH]) -> usize {
+        // The target name in the buffer is a null-terminated C string
+        // but often the rest of the buffer contains garbage.
+        // So the name is cut at the first null byte before decoding it.
+        let target_name_field = &buffer[11..266];
+
+        // If no null byte is found, the whole field of 255 bytes is used.
+        // The name is ignored completely if the target is not flagged as named.
+        if buffer[7] == 0 {
+            0
+        } else {
+            target_name_field
+                .iter()
+                .position(|byte| *byte == 0)
+                .unwrap_or(target_name_field.len())
+        }
+    }
+
+    /// Returns the target prefix as bytes in file order.
+    ///
+    /// The `bTargetNamed` flag is derived from the presence of a name.
+    /// Names longer than the field of 255 bytes are truncated.
+    pub fn to_bytes(&self) -> [u8; TargetPrefix::LENGTH] {
+        let mut buffer = [0; TargetPrefix::LENGTH];
+
+        for (dest, src) in buffer[0..6].iter_mut().zip(self.szSignature.as_bytes()) {
+            *dest = *src;
+        }
+
+        buffer[6] = self.bAlternateSetting;
+        buffer[7..11].copy_from_slice(&(!self.szTargetName.is_empty() as u32).to_le_bytes());
+
+        for (dest, src) in buffer[11..266].iter_mut().zip(self.szTargetName.as_bytes()) {
+            *dest = *src;
+        }
+
+        buffer[266..270].copy_from_slice(&self.dwTargetSize.to_le_bytes());
+        buffer[270..274].copy_from_slice(&self.dwNbElements.to_le_bytes());
+
+        buffer
+    }
+
+    /// Sets the target name and flags the target as named if the name
+    /// is not empty.
+    ///
+    /// Names containing null bytes or longer than 254 bytes are rejected,
+    /// as they would not be read back unchanged. The last byte of the field
+    /// of 255 bytes is reserved for the null terminator, otherwise the name
+    /// would be reported as possibly truncated by
+    /// [`DfuFile::open_with_warnings`](crate::DfuFile::open_with_warnings).
+    pub fn set_name(&mut self, name: &str) -> Result<()> {
+        if name.contains('\0') {
+            return Err(anyhow!(Error::TargetNameContainsNull));
+        }
+
+        if name.len() > 254 {
+            return Err(anyhow!(Error::TargetNameTooLong(name.len())));
+        }
+
+        self.szTargetName = name.to_string();
+        self.bTargetNamed = !name.is_empty() as u8;
+
+        Ok(())
+    }
+
+    /// Returns the alternate setting.
+    pub fn alt_setting(&self) -> u8 {
+        self.bAlternateSetting
+    }
+
+    /// Returns the target name.
+    pub fn name(&self) -> &str {
+        &self.szTargetName
+    }
+
+    /// Returns the declared target size in bytes.
+    pub fn target_size(&self) -> u32 {
+        self.dwTargetSize
+    }
+
+    /// Returns the declared number of elements.
+    pub fn element_count(&self) -> u32 {
+        self.dwNbElements
+    }
+
+    /// Returns if the target is flagged as named.
+    pub fn is_named(&self) -> bool {
+        self.bTargetNamed != 0
+    }
+
     /// Creates a new target prefix by reading a file.
     ///
     /// The `file_pos` argument must be set to the postion inside the file as
     /// offset from the start and is updated according to the number of bytes read.
-    pub fn from_file(file: &mut std::fs::File, file_pos: &mut u64) -> Result<Self> {
+    pub fn from_file<R: Read + Seek>(file: &mut R, file_pos: &mut u64) -> Result<Self> {
         file.seek(std::io::SeekFrom::Start(*file_pos))?;
-        let mut buffer = [0; TARGET_PREFIX_LENGTH];
+        let mut buffer = [0; TargetPrefix::LENGTH];
         file.read_exact(&mut buffer)?;
 
-        *file_pos += TARGET_PREFIX_LENGTH as u64;
+        *file_pos += TargetPrefix::LENGTH as u64;
 
+        Self::check_signature(&buffer)?;
         let data = Self::from_bytes(&buffer);
 
-        if &data.szSignature != "Target" {
-            return Err(anyhow!(Error::InvalidTargetPrefixSignature));
-        }
+        Ok(data)
+    }
+
+    /// Overwrites the target prefix by reading a file, using a caller-provided
+    /// scratch buffer.
+    ///
+    /// This is the non-allocating counterpart of [`TargetPrefix::from_file`],
+    /// see [`TargetPrefix::update_from_bytes`]. The `file_pos` argument is
+    /// handled the same way.
+    pub fn update_from_file<R: Read + Seek>(
+        &mut self,
+        file: &mut R,
+        file_pos: &mut u64,
+        buffer: &mut [u8; TargetPrefix::LENGTH],
+    ) -> Result<()> {
+        file.seek(std::io::SeekFrom::Start(*file_pos))?;
+        file.read_exact(buffer)?;
+
+        *file_pos += TargetPrefix::LENGTH as u64;
+
+        Self::check_signature(buffer)?;
+        self.update_from_bytes(buffer);
+
+        Ok(())
+    }
+
+    /// Creates a new target prefix by reading a file asynchronously.
+    ///
+    /// The `file_pos` argument must be set to the postion inside the file as
+    /// offset from the start and is updated according to the number of bytes read.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async<R: AsyncRead + AsyncSeek + Unpin>(
+        file: &mut R,
+        file_pos: &mut u64,
+    ) -> Result<Self> {
+        file.seek(std::io::SeekFrom::Start(*file_pos)).await?;
+        let mut buffer = [0; TargetPrefix::LENGTH];
+        file.read_exact(&mut buffer).await?;
+
+        *file_pos += TargetPrefix::LENGTH as u64;
+
+        Self::check_signature(&buffer)?;
+        let data = Self::from_bytes(&buffer);
 
         Ok(data)
     }
+
+    /// Check the raw signature bytes of a target prefix buffer.
+    fn check_signature(buffer: &[u8; TargetPrefix::LENGTH]) -> Result<()> {
+        if &buffer[0..6] != b"Target" {
+            let mut found = [0; 6];
+            found.copy_from_slice(&buffer[0..6]);
+            return Err(anyhow!(Error::InvalidTargetPrefixSignature { found }));
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<&[u8]> for TargetPrefix {
+    type Error = Error;
+
+    /// Creates a new target prefix from a slice, which must have the
+    /// target prefix length.
+    fn try_from(buffer: &[u8]) -> Result<Self, Self::Error> {
+        let buffer = buffer.try_into().map_err(|_| Error::LengthMismatch {
+            expected: TargetPrefix::LENGTH,
+            actual: buffer.len(),
+        })?;
+
+        Ok(Self::from_bytes(buffer))
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
 /// Length of the image element without data in bytes.
-pub const IMAGE_ELEMENT_LENGTH: usize = 8;
+#[deprecated(since = "0.3.0", note = "use `ImageElement::LENGTH` instead")]
+pub const IMAGE_ELEMENT_LENGTH: usize = ImageElement::LENGTH;
 
 /// An image element, see UM0391 section 2.3.3.
 ///
 /// The image element provides a data record containing the effective
 /// firmware data preceded by the data address and data size.
 #[allow(non_snake_case)]
-#[derive(Debug, Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct ImageElement {
     /// Starting address of the data.
     pub dwElementAddress: u32,
@@ -332,6 +1842,31 @@ pub struct ImageElement {
 
     /// File position of data as offset from the start.
     pub data_position: u64,
+
+    /// Data held in memory, used instead of reading from the file if present.
+    pub data: Option<Vec<u8>>,
+}
+
+impl std::fmt::Debug for ImageElement {
+    /// Formats the element with address, size and position in hexadecimal
+    /// notation. In-memory data is shown by its length only.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut debug_struct = f.debug_struct("ImageElement");
+        debug_struct
+            .field(
+                "dwElementAddress",
+                &format_args!("0x{:08X}", self.dwElementAddress),
+            )
+            .field("dwElementSize", &format_args!("0x{:X}", self.dwElementSize))
+            .field("data_position", &format_args!("0x{:X}", self.data_position));
+
+        match &self.data {
+            Some(data) => debug_struct.field("data", &format_args!("Some({} bytes)", data.len())),
+            None => debug_struct.field("data", &format_args!("None")),
+        };
+
+        debug_struct.finish()
+    }
 }
 
 impl Default for ImageElement {
@@ -341,22 +1876,64 @@ impl Default for ImageElement {
             dwElementAddress: 0,
             dwElementSize: 0,
             data_position: 0,
+            data: None,
         }
     }
 }
 
 impl ImageElement {
+    /// Length of the image element without data in bytes.
+    pub const LENGTH: usize = 8;
+
     /// Creates a new image element.
     pub fn new(element_address: u32, element_size: u32, data_position: u64) -> Self {
         Self {
             dwElementAddress: element_address,
             dwElementSize: element_size,
             data_position,
+            data: None,
         }
     }
 
+    /// Creates a new image element holding its data in memory.
+    ///
+    /// The size is set according to the data. The data position is
+    /// left at 0, as it is only determined when writing a file.
+    ///
+    /// Returns an error if the data does not fit into the size field.
+    pub fn with_data(element_address: u32, data: Vec<u8>) -> Result<Self> {
+        let element_size = u32::try_from(data.len()).map_err(|_| anyhow!(Error::ImageTooLarge))?;
+
+        Ok(Self {
+            dwElementAddress: element_address,
+            dwElementSize: element_size,
+            data_position: 0,
+            data: Some(data),
+        })
+    }
+
+    /// Returns the key of the element, consisting of address and size.
+    pub fn key(&self) -> ElementKey {
+        ElementKey::new(self.dwElementAddress, self.dwElementSize)
+    }
+
+    /// Returns the start address.
+    pub fn address(&self) -> u32 {
+        self.dwElementAddress
+    }
+
+    /// Returns the data size in bytes.
+    pub fn size(&self) -> u32 {
+        self.dwElementSize
+    }
+
+    /// Returns the data if it is held in memory.
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+
     /// Creates a new image element from a buffer of u8 values and data position.
-    pub fn from_bytes(buffer: &[u8; IMAGE_ELEMENT_LENGTH], data_position: u64) -> Self {
+    pub fn from_bytes(buffer: &[u8; ImageElement::LENGTH], data_position: u64) -> Self {
         Self::new(
             u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]),
             u32::from_le_bytes([buffer[4], buffer[5], buffer[6], buffer[7]]),
@@ -364,16 +1941,55 @@ impl ImageElement {
         )
     }
 
+    /// Returns the element header as bytes in file order.
+    pub fn to_bytes(&self) -> [u8; ImageElement::LENGTH] {
+        let mut buffer = [0; ImageElement::LENGTH];
+        buffer[0..4].copy_from_slice(&self.dwElementAddress.to_le_bytes());
+        buffer[4..8].copy_from_slice(&self.dwElementSize.to_le_bytes());
+
+        buffer
+    }
+
+    /// Check if the start address is a multiple of `alignment`.
+    ///
+    /// An alignment of 0 is never met.
+    pub fn is_aligned(&self, alignment: u32) -> bool {
+        self.dwElementAddress.checked_rem(alignment) == Some(0)
+    }
+
     /// Creates a new image element by reading a file.
     ///
     /// The `file_pos` argument must be set to the postion inside the file as
     /// offset from the start and is updated according to the number of bytes read.
-    pub fn from_file(file: &mut std::fs::File, file_pos: &mut u64) -> Result<Self> {
+    pub fn from_file<R: Read + Seek>(file: &mut R, file_pos: &mut u64) -> Result<Self> {
         file.seek(std::io::SeekFrom::Start(*file_pos))?;
-        let mut buffer = [0; IMAGE_ELEMENT_LENGTH];
+        let mut buffer = [0; ImageElement::LENGTH];
         file.read_exact(&mut buffer)?;
 
-        *file_pos += IMAGE_ELEMENT_LENGTH as u64;
+        *file_pos += ImageElement::LENGTH as u64;
+
+        let data = Self::from_bytes(&buffer, *file_pos);
+
+        // Skip the data without reading it
+        *file_pos = file.seek(std::io::SeekFrom::Current(data.dwElementSize as i64))?;
+
+        Ok(data)
+    }
+
+    /// Creates a new image element by reading a file asynchronously.
+    ///
+    /// The `file_pos` argument must be set to the postion inside the file as
+    /// offset from the start and is updated according to the number of bytes read.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async<R: AsyncRead + AsyncSeek + Unpin>(
+        file: &mut R,
+        file_pos: &mut u64,
+    ) -> Result<Self> {
+        file.seek(std::io::SeekFrom::Start(*file_pos)).await?;
+        let mut buffer = [0; ImageElement::LENGTH];
+        file.read_exact(&mut buffer).await?;
+
+        *file_pos += ImageElement::LENGTH as u64;
 
         let data = Self::from_bytes(&buffer, *file_pos);
 
@@ -388,20 +2004,321 @@ impl ImageElement {
     /// The function tries to fill the buffer completely and returns the
     /// number of valid bytes in the buffer. This may be less than the buffer
     /// size in case of EOF or reaching the element borders.
-    pub fn read_at(
+    ///
+    /// If the element holds its data in memory, the file is not accessed.
+    pub fn read_at<R: Read + Seek>(
         &self,
-        file: &mut std::fs::File,
+        file: &mut R,
         position: u32,
         buffer: &mut [u8],
     ) -> Result<usize> {
+        if let Some(data) = &self.data {
+            let start = std::cmp::min(position as usize, data.len());
+            let read_size = std::cmp::min(buffer.len(), data.len() - start);
+            buffer[..read_size].copy_from_slice(&data[start..start + read_size]);
+
+            return Ok(read_size);
+        }
+
+        if position >= self.dwElementSize {
+            return Ok(0);
+        }
+
+        let read_size = std::cmp::min(buffer.len(), (self.dwElementSize - position) as usize);
         let file_pos = self.data_position + (position as u64);
         file.seek(std::io::SeekFrom::Start(file_pos))?;
-        let read_size = file.read(buffer)?;
 
-        let read_size = std::cmp::min(read_size, (self.dwElementSize - position) as usize);
+        // Reading stops at EOF, so no data beyond the end of the file is
+        // returned, even if the element size exceeds it.
+        let read_size = file.read(&mut buffer[..read_size])?;
+
+        Ok(read_size)
+    }
+
+    /// Read data from file into a buffer asynchronously.
+    ///
+    /// See [`ImageElement::read_at`] for details.
+    #[cfg(feature = "tokio")]
+    pub async fn read_at_async<R: AsyncRead + AsyncSeek + Unpin>(
+        &self,
+        file: &mut R,
+        position: u32,
+        buffer: &mut [u8],
+    ) -> Result<usize> {
+        if let Some(data) = &self.data {
+            let start = std::cmp::min(position as usize, data.len());
+            let read_size = std::cmp::min(buffer.len(), data.len() - start);
+            buffer[..read_size].copy_from_slice(&data[start..start + read_size]);
+
+            return Ok(read_size);
+        }
+
+        if position >= self.dwElementSize {
+            return Ok(0);
+        }
+
+        let read_size = std::cmp::min(buffer.len(), (self.dwElementSize - position) as usize);
+        let file_pos = self.data_position + (position as u64);
+        file.seek(std::io::SeekFrom::Start(file_pos)).await?;
+        let read_size = file.read(&mut buffer[..read_size]).await?;
 
         Ok(read_size)
     }
+
+    /// Check if the data of the element fully exists.
+    ///
+    /// Returns `false` if the element size exceeds the end of the file,
+    /// which indicates a corrupt or truncated file.
+    pub fn verify_data_present<R: Read + Seek>(&self, file: &mut R) -> Result<bool> {
+        if let Some(data) = &self.data {
+            return Ok(data.len() as u64 >= self.dwElementSize as u64);
+        }
+
+        let file_size = file.seek(std::io::SeekFrom::End(0))?;
+
+        Ok(self.data_position + self.dwElementSize as u64 <= file_size)
+    }
+
+    /// Read the complete data of the element into a caller-provided buffer.
+    ///
+    /// The buffer length must match the element size exactly.
+    pub fn read_exact_into<R: Read + Seek>(&self, file: &mut R, buffer: &mut [u8]) -> Result<()> {
+        if buffer.len() != self.dwElementSize as usize {
+            return Err(anyhow!(Error::LengthMismatch {
+                expected: self.dwElementSize as usize,
+                actual: buffer.len(),
+            }));
+        }
+
+        if let Some(data) = &self.data {
+            let data = data.get(..buffer.len()).ok_or_else(|| {
+                anyhow!(Error::LengthMismatch {
+                    expected: buffer.len(),
+                    actual: data.len(),
+                })
+            })?;
+            buffer.copy_from_slice(data);
+
+            return Ok(());
+        }
+
+        file.seek(std::io::SeekFrom::Start(self.data_position))?;
+        file.read_exact(buffer)?;
+
+        Ok(())
+    }
+
+    /// Copy the data of the element into a writer through a buffer of
+    /// `buf_size` bytes.
+    ///
+    /// Unlike [`read_all()`](Self::read_all), the memory usage is bounded by
+    /// the buffer size, regardless of the element size. Copying stops after
+    /// `dwElementSize` bytes and fails if the file ends before.
+    ///
+    /// Returns the number of bytes copied.
+    pub fn copy_to<R: Read + Seek, W: Write>(
+        &self,
+        file: &mut R,
+        sink: &mut W,
+        buf_size: usize,
+    ) -> Result<u64> {
+        if buf_size == 0 {
+            return Err(anyhow!(Error::InvalidBufferSize));
+        }
+
+        let mut buffer = vec![0; std::cmp::min(buf_size, self.dwElementSize as usize)];
+        let mut position = 0;
+
+        while position < self.dwElementSize {
+            let read_size = self.read_at(file, position, &mut buffer)?;
+
+            if read_size == 0 {
+                return Err(anyhow!(std::io::Error::from(
+                    std::io::ErrorKind::UnexpectedEof
+                )));
+            }
+
+            sink.write_all(&buffer[..read_size])?;
+            position += read_size as u32;
+        }
+
+        Ok(position as u64)
+    }
+
+    /// Check if the element data is equal to the content of an external file.
+    pub fn matches_file<R: Read + Seek, P: AsRef<std::path::Path>>(
+        &self,
+        file: &mut R,
+        external_path: P,
+    ) -> Result<bool> {
+        Ok(self.first_difference(file, external_path)?.is_none())
+    }
+
+    /// Compare the element data to the content of an external file.
+    ///
+    /// Returns the offset of the first differing byte or `None` if both
+    /// are equal. If the lengths differ, but the shorter data matches the
+    /// start of the longer one, the length of the shorter one is returned.
+    pub fn first_difference<R: Read + Seek, P: AsRef<std::path::Path>>(
+        &self,
+        file: &mut R,
+        external_path: P,
+    ) -> Result<Option<u64>> {
+        let mut external_file = std::fs::File::open(external_path)?;
+        let external_size = external_file.metadata()?.len();
+
+        const CHUNK_SIZE: usize = 1024;
+        let mut buffer = [0; CHUNK_SIZE];
+        let mut external_buffer = [0; CHUNK_SIZE];
+        let mut position = 0;
+
+        loop {
+            let read_size = self.read_at(file, position, &mut buffer)?;
+
+            if read_size == 0 {
+                break;
+            }
+
+            let mut external_read_size = 0;
+
+            while external_read_size < read_size {
+                let size =
+                    external_file.read(&mut external_buffer[external_read_size..read_size])?;
+
+                if size == 0 {
+                    break;
+                }
+
+                external_read_size += size;
+            }
+
+            let difference = buffer[..external_read_size]
+                .iter()
+                .zip(&external_buffer[..external_read_size])
+                .position(|(byte, external_byte)| byte != external_byte);
+
+            if let Some(offset) = difference {
+                return Ok(Some(position as u64 + offset as u64));
+            }
+
+            if external_read_size < read_size {
+                return Ok(Some(position as u64 + external_read_size as u64));
+            }
+
+            position += read_size as u32;
+        }
+
+        if position != self.dwElementSize || position as u64 != external_size {
+            return Ok(Some(position as u64));
+        }
+
+        Ok(None)
+    }
+
+    /// Read the data of the element from the file into memory,
+    /// unless it is already held in memory.
+    pub fn load_data<R: Read + Seek>(&mut self, file: &mut R) -> Result<()> {
+        if self.data.is_none() {
+            self.data = Some(self.read_all(file)?);
+        }
+
+        Ok(())
+    }
+
+    /// Calculate the standard CRC32 checksum over the element data.
+    ///
+    /// Unlike the file CRC in the suffix, the result is not inverted,
+    /// so it matches common tools like `crc32` or zlib.
+    pub fn crc32<R: Read + Seek>(&self, file: &mut R) -> Result<u32> {
+        let mut crc = crate::crc32::Crc32::new();
+
+        if let Some(data) = &self.data {
+            crc.update(data);
+            return Ok(crc.finalize());
+        }
+
+        file.seek(std::io::SeekFrom::Start(self.data_position))?;
+
+        const CHUNK_SIZE: u64 = 1024;
+        let mut buffer = [0; CHUNK_SIZE as usize];
+        let mut remaining = self.dwElementSize as u64;
+
+        while remaining > 0 {
+            let read_size = std::cmp::min(CHUNK_SIZE, remaining) as usize;
+            file.read_exact(&mut buffer[..read_size])?;
+            crc.update(&buffer[..read_size]);
+            remaining -= read_size as u64;
+        }
+
+        Ok(crc.finalize())
+    }
+
+    /// Read the complete data of the element.
+    ///
+    /// If the element holds its data in memory, a copy of it is returned.
+    pub fn read_all<R: Read + Seek>(&self, file: &mut R) -> Result<Vec<u8>> {
+        if let Some(data) = &self.data {
+            return Ok(data.clone());
+        }
+
+        file.seek(std::io::SeekFrom::Start(self.data_position))?;
+
+        // The buffer grows with the data actually read, so a corrupt element
+        // size does not cause a huge allocation up front.
+        let mut buffer = Vec::new();
+        file.take(self.dwElementSize as u64)
+            .read_to_end(&mut buffer)?;
+
+        if buffer.len() != self.dwElementSize as usize {
+            return Err(anyhow!(std::io::Error::from(
+                std::io::ErrorKind::UnexpectedEof
+            )));
+        }
+
+        Ok(buffer)
+    }
+
+    /// Read the complete data of the element as UTF-8 text.
+    ///
+    /// This is a convenience for elements known to hold text like
+    /// configuration data and not intended for binary firmware.
+    /// Invalid UTF-8 sequences result in an error.
+    pub fn read_utf8<R: Read + Seek>(&self, file: &mut R) -> Result<String> {
+        Ok(String::from_utf8(self.read_all(file)?)?)
+    }
+
+    /// Read the complete data of the element as UTF-8 text, with invalid
+    /// sequences replaced.
+    ///
+    /// Like [`read_utf8()`](Self::read_utf8), this is not intended for
+    /// binary firmware.
+    pub fn read_utf8_lossy<R: Read + Seek>(&self, file: &mut R) -> Result<String> {
+        Ok(String::from_utf8_lossy(&self.read_all(file)?).into_owned())
+    }
+
+    /// Read the complete data of the element as [`bytes::Bytes`],
+    /// which can be cloned and sliced cheaply.
+    #[cfg(feature = "bytes")]
+    pub fn read_bytes<R: Read + Seek>(&self, file: &mut R) -> Result<bytes::Bytes> {
+        Ok(bytes::Bytes::from(self.read_all(file)?))
+    }
+}
+
+impl TryFrom<&[u8]> for ImageElement {
+    type Error = Error;
+
+    /// Creates a new image element from a slice, which must have the
+    /// image element length without data.
+    ///
+    /// The data position is set to 0, as it is unknown.
+    fn try_from(buffer: &[u8]) -> Result<Self, Self::Error> {
+        let buffer = buffer.try_into().map_err(|_| Error::LengthMismatch {
+            expected: ImageElement::LENGTH,
+            actual: buffer.len(),
+        })?;
+
+        Ok(Self::from_bytes(buffer, 0))
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -410,27 +2327,205 @@ impl ImageElement {
 #[derive(Debug)]
 pub enum Error {
     /// File prefix signature is not "DfuSe".
-    InvalidPrefixSignature,
+    InvalidPrefixSignature {
+        /// Signature bytes found in the file.
+        found: [u8; 5],
+    },
 
     /// Target prefix signature is not "Target".
-    InvalidTargetPrefixSignature,
+    InvalidTargetPrefixSignature {
+        /// Signature bytes found in the file.
+        found: [u8; 6],
+    },
 
     /// File is too small (smaller than prefix + suffix size).
     InsufficientFileSize,
+
+    /// Address ranges of image elements overlap.
+    OverlappingElements,
+
+    /// Index does not refer to an existing image element.
+    InvalidElementIndex,
+
+    /// Index does not refer to an existing image.
+    InvalidImageIndex,
+
+    /// Alternate setting is already used by another image.
+    DuplicateAltSetting(u8),
+
+    /// Prefix format revision is not supported.
+    UnsupportedDfuSeVersion(u8),
+
+    /// Alignment must not be zero.
+    InvalidAlignment,
+
+    /// Operation requires the element data to be held in memory.
+    ElementDataNotLoaded,
+
+    /// Maximum element size must not be zero.
+    InvalidMaxSize,
+
+    /// Buffer size must not be zero.
+    InvalidBufferSize,
+
+    /// File has a DfuSe prefix, but an invalid suffix.
+    InvalidSuffix,
+
+    /// Target name contains a null byte.
+    TargetNameContainsNull,
+
+    /// Target name with the given length in bytes does not fit into the field
+    /// of 255 bytes with null terminator.
+    TargetNameTooLong(usize),
+
+    /// Image element has a size of zero.
+    EmptyElement {
+        /// Index of the image containing the element.
+        image: usize,
+
+        /// Index of the element inside the image.
+        index: usize,
+    },
+
+    /// Declared size exceeds a limit set in the parse options.
+    SizeLimitExceeded {
+        /// Declared size in bytes.
+        declared: u64,
+
+        /// Limit in bytes.
+        limit: u64,
+    },
+
+    /// Image element data extends beyond the end of the file.
+    ElementDataMissing {
+        /// Index of the image containing the element.
+        image: usize,
+
+        /// Index of the element inside the image.
+        index: usize,
+    },
+
+    /// Memory layout descriptor string is malformed.
+    InvalidLayoutDescriptor,
+
+    /// Address range of an image element is not inside the memory layout.
+    ElementOutsideLayout {
+        /// Alternate setting of the image containing the element.
+        alt_setting: u8,
+
+        /// Index of the element inside the image.
+        index: usize,
+
+        /// Start address of the element.
+        address: u32,
+    },
+
+    /// Size exceeds the range of the size fields.
+    ImageTooLarge,
+
+    /// Address exceeds the 32-bit address space.
+    AddressOverflow,
+
+    /// Buffer length does not match the required length.
+    LengthMismatch {
+        /// Required length in bytes.
+        expected: usize,
+
+        /// Actual length in bytes.
+        actual: usize,
+    },
 }
 
 impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Self::InvalidPrefixSignature => "Invalid file prefix signature",
-                Self::InvalidTargetPrefixSignature => "Invalid target prefix signature",
-                Self::InsufficientFileSize => "File size is to small to contain prefix and suffix",
+        match self {
+            Self::InvalidPrefixSignature { found } => {
+                write!(
+                    f,
+                    "Invalid file prefix signature \"{}\"",
+                    escape_bytes(found)
+                )
             }
-        )
+            Self::InvalidTargetPrefixSignature { found } => {
+                write!(
+                    f,
+                    "Invalid target prefix signature \"{}\"",
+                    escape_bytes(found)
+                )
+            }
+            Self::InsufficientFileSize => {
+                write!(f, "File size is to small to contain prefix and suffix")
+            }
+            Self::OverlappingElements => write!(f, "Address ranges of image elements overlap"),
+            Self::InvalidElementIndex => write!(f, "Invalid image element index"),
+            Self::InvalidImageIndex => write!(f, "Invalid image index"),
+            Self::DuplicateAltSetting(alt_setting) => {
+                write!(f, "Alternate setting {alt_setting} is already in use")
+            }
+            Self::UnsupportedDfuSeVersion(version) => {
+                write!(f, "Unsupported DfuSe version {version}")
+            }
+            Self::InvalidAlignment => write!(f, "Alignment must not be zero"),
+            Self::ElementDataNotLoaded => write!(f, "Element data is not loaded into memory"),
+            Self::InvalidMaxSize => write!(f, "Maximum element size must not be zero"),
+            Self::InvalidBufferSize => write!(f, "Buffer size must not be zero"),
+            Self::InvalidSuffix => write!(f, "DfuSe file has an invalid suffix"),
+            Self::TargetNameContainsNull => write!(f, "Target name contains a null byte"),
+            Self::TargetNameTooLong(length) => {
+                write!(
+                    f,
+                    "Target name of {length} bytes exceeds the maximum of 254"
+                )
+            }
+            Self::EmptyElement { image, index } => {
+                write!(f, "Element {index} of image {image} has a size of zero")
+            }
+            Self::SizeLimitExceeded { declared, limit } => {
+                write!(
+                    f,
+                    "Declared size of {declared} bytes exceeds the limit of {limit}"
+                )
+            }
+            Self::ElementDataMissing { image, index } => {
+                write!(f, "Data of element {index} of image {image} is missing")
+            }
+            Self::InvalidLayoutDescriptor => write!(f, "Invalid memory layout descriptor"),
+            Self::ElementOutsideLayout {
+                alt_setting,
+                index,
+                address,
+            } => {
+                write!(
+                    f,
+                    "Element {index} of alternate setting {alt_setting} at address 0x{address:08X} is outside the memory layout"
+                )
+            }
+            Self::ImageTooLarge => write!(f, "Image is too large"),
+            Self::AddressOverflow => write!(f, "Address exceeds the 32-bit address space"),
+            Self::LengthMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Length mismatch, expected {expected} bytes, got {actual}"
+                )
+            }
+        }
     }
 }
+
+/// Replaces the content of a string with bytes decoded as UTF-8, with
+/// invalid sequences replaced, reusing the allocation of the string.
+fn replace_lossy(dest: &mut String, bytes: &[u8]) {
+    dest.clear();
+    dest.push_str(&String::from_utf8_lossy(bytes));
+}
+
+/// Escape bytes for display, keeping printable ASCII characters.
+fn escape_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .flat_map(|byte| std::ascii::escape_default(*byte))
+        .map(char::from)
+        .collect()
+}
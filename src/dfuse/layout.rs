@@ -0,0 +1,251 @@
+//! Memory layout descriptors of DfuSe devices.
+//!
+//! DfuSe devices describe the memory behind each alternate setting with
+//! a string like `@Internal Flash  /0x08000000/04*016Kg,01*064Kg,07*128Kg`.
+//! It consists of the memory name, followed by one or more regions, each
+//! given as start address and a list of sector groups. A sector group is
+//! written as `<count>*<size><unit><type>`, where the unit is one of
+//! ` ` (bytes), `K` (kilobytes) or `M` (megabytes) and the type is a letter
+//! encoding the access permissions.
+
+use anyhow::anyhow;
+
+use crate::Result;
+
+use super::Error;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Memory layout of an alternate setting.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryLayout {
+    /// Name of the memory, e.g. "Internal Flash".
+    pub name: String,
+
+    /// Contiguous memory regions.
+    pub regions: Vec<MemoryRegion>,
+}
+
+impl MemoryLayout {
+    /// Creates a new layout.
+    pub fn new(name: String, regions: Vec<MemoryRegion>) -> Self {
+        Self { name, regions }
+    }
+
+    /// Creates a new layout by parsing a descriptor string.
+    pub fn parse(descriptor: &str) -> Result<Self> {
+        let mut parts = descriptor.split('/');
+
+        let name = parts
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('@')
+            .trim()
+            .to_string();
+
+        let mut regions = Vec::new();
+
+        while let Some(address) = parts.next() {
+            let address = address.trim();
+
+            // Tolerate a trailing separator
+            if address.is_empty() {
+                continue;
+            }
+
+            let address = u32::from_str_radix(
+                address
+                    .strip_prefix("0x")
+                    .or_else(|| address.strip_prefix("0X"))
+                    .unwrap_or(address),
+                16,
+            )
+            .map_err(|_| anyhow!(Error::InvalidLayoutDescriptor))?;
+
+            let sectors = parts
+                .next()
+                .ok_or_else(|| anyhow!(Error::InvalidLayoutDescriptor))?
+                .split(',')
+                .map(SectorGroup::parse)
+                .collect::<Result<Vec<_>>>()?;
+
+            regions.push(MemoryRegion::new(address, sectors));
+        }
+
+        Ok(Self::new(name, regions))
+    }
+
+    /// Returns the region containing the address range of `size` bytes
+    /// starting at `address` completely.
+    pub fn find_region(&self, address: u32, size: u32) -> Option<&MemoryRegion> {
+        self.regions
+            .iter()
+            .find(|region| region.contains(address, size))
+    }
+}
+
+impl std::str::FromStr for MemoryLayout {
+    type Err = anyhow::Error;
+
+    fn from_str(descriptor: &str) -> Result<Self> {
+        Self::parse(descriptor)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Contiguous region of memory made up of sectors.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryRegion {
+    /// Start address.
+    pub address: u32,
+
+    /// Groups of sectors in ascending address order.
+    pub sectors: Vec<SectorGroup>,
+}
+
+impl MemoryRegion {
+    /// Creates a new region.
+    pub fn new(address: u32, sectors: Vec<SectorGroup>) -> Self {
+        Self { address, sectors }
+    }
+
+    /// Returns the size of the region in bytes.
+    pub fn size(&self) -> u64 {
+        self.sectors.iter().map(SectorGroup::size).sum()
+    }
+
+    /// Returns the address following the end of the region.
+    pub fn end(&self) -> u64 {
+        self.address as u64 + self.size()
+    }
+
+    /// Check if the address range of `size` bytes starting at `address`
+    /// lies completely inside the region.
+    pub fn contains(&self, address: u32, size: u32) -> bool {
+        address >= self.address && address as u64 + size as u64 <= self.end()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Group of consecutive sectors with equal size and type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SectorGroup {
+    /// Number of sectors.
+    pub count: u32,
+
+    /// Size of a single sector in bytes.
+    pub sector_size: u32,
+
+    /// Type letter encoding the access permissions.
+    pub sector_type: char,
+}
+
+impl SectorGroup {
+    /// Creates a new sector group.
+    pub fn new(count: u32, sector_size: u32, sector_type: char) -> Self {
+        Self {
+            count,
+            sector_size,
+            sector_type,
+        }
+    }
+
+    /// Creates a new sector group by parsing a descriptor like `04*016Kg`.
+    pub fn parse(descriptor: &str) -> Result<Self> {
+        let (count, size) = descriptor
+            .trim()
+            .split_once('*')
+            .ok_or_else(|| anyhow!(Error::InvalidLayoutDescriptor))?;
+
+        let count = count
+            .parse::<u32>()
+            .map_err(|_| anyhow!(Error::InvalidLayoutDescriptor))?;
+
+        let digits = size
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(size.len());
+        let (size, suffix) = size.split_at(digits);
+
+        let size = size
+            .parse::<u32>()
+            .map_err(|_| anyhow!(Error::InvalidLayoutDescriptor))?;
+
+        let mut suffix = suffix.chars();
+
+        let (multiplier, sector_type) = match (suffix.next(), suffix.next(), suffix.next()) {
+            (Some(sector_type), None, None) => (1, sector_type),
+            (Some(' ') | Some('B'), Some(sector_type), None) => (1, sector_type),
+            (Some('K'), Some(sector_type), None) => (1024, sector_type),
+            (Some('M'), Some(sector_type), None) => (1024 * 1024, sector_type),
+            _ => return Err(anyhow!(Error::InvalidLayoutDescriptor)),
+        };
+
+        let sector_size = size
+            .checked_mul(multiplier)
+            .ok_or_else(|| anyhow!(Error::InvalidLayoutDescriptor))?;
+
+        Ok(Self::new(count, sector_size, sector_type))
+    }
+
+    /// Returns the total size of all sectors in bytes.
+    pub fn size(&self) -> u64 {
+        self.count as u64 * self.sector_size as u64
+    }
+
+    /// Returns the access permissions decoded from the type letter,
+    /// or `None` if the letter is not one of `a` to `g` or `A` to `G`.
+    pub fn attributes(&self) -> Option<SectorAttributes> {
+        SectorAttributes::from_letter(self.sector_type)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Access permissions of sectors.
+///
+/// They are encoded in the type letter, which is one of `a` to `g`, with the
+/// uppercase variants being equivalent. The three lowest bits of the letter
+/// flag the sectors as readable (bit 0), erasable (bit 1) and writable
+/// (bit 2), e.g. `a` means read-only and `g` means fully accessible.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SectorAttributes {
+    /// Sectors can be read.
+    pub readable: bool,
+
+    /// Sectors can be erased.
+    pub erasable: bool,
+
+    /// Sectors can be written.
+    pub writable: bool,
+}
+
+impl SectorAttributes {
+    /// Decodes the attributes from a type letter.
+    pub fn from_letter(letter: char) -> Option<Self> {
+        if !matches!(letter, 'a'..='g' | 'A'..='G') {
+            return None;
+        }
+
+        let bits = letter as u8 & 0x07;
+
+        Some(Self {
+            readable: bits & 0x01 != 0,
+            erasable: bits & 0x02 != 0,
+            writable: bits & 0x04 != 0,
+        })
+    }
+
+    /// Returns the lowercase type letter encoding the attributes,
+    /// or `None` if no permission is set.
+    pub fn to_letter(&self) -> Option<char> {
+        let bits = self.readable as u8 | (self.erasable as u8) << 1 | (self.writable as u8) << 2;
+
+        if bits == 0 {
+            None
+        } else {
+            Some((b'a' - 1 + bits) as char)
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Flash sector maps of STM32 devices.
+//!
+//! Flash memory can only be erased in whole sectors, so writing a single
+//! byte into a sector erases all other data in it. The sector maps of the
+//! supported families allow to determine which sectors are affected by
+//! the elements of an image.
+
+use super::layout::{MemoryLayout, MemoryRegion, SectorGroup};
+use super::Image;
+
+/// Start address of the internal flash.
+const FLASH_ADDRESS: u32 = 0x08000000;
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// STM32 device family with a known internal flash layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Family {
+    /// STM32F1 medium-density devices with 128 pages of 1 KB.
+    F1MediumDensity,
+
+    /// STM32F1 high-density devices with 256 pages of 2 KB.
+    F1HighDensity,
+
+    /// STM32F401xD/E devices with 512 KB of flash.
+    F401,
+
+    /// STM32F405/407/415/417 devices with 1 MB of flash.
+    F40x,
+
+    /// STM32F427/429/437/439 devices with 2 MB of flash in two banks.
+    F42x,
+
+    /// STM32F74x/75x devices with 1 MB of flash.
+    F74x,
+}
+
+impl Family {
+    /// Returns the layout of the internal flash.
+    pub fn layout(&self) -> MemoryLayout {
+        let sectors = match self {
+            Self::F1MediumDensity => vec![SectorGroup::new(128, 1024, 'g')],
+            Self::F1HighDensity => vec![SectorGroup::new(256, 2048, 'g')],
+            Self::F401 => vec![
+                SectorGroup::new(4, 16 * 1024, 'g'),
+                SectorGroup::new(1, 64 * 1024, 'g'),
+                SectorGroup::new(3, 128 * 1024, 'g'),
+            ],
+            Self::F40x => vec![
+                SectorGroup::new(4, 16 * 1024, 'g'),
+                SectorGroup::new(1, 64 * 1024, 'g'),
+                SectorGroup::new(7, 128 * 1024, 'g'),
+            ],
+            Self::F42x => vec![
+                SectorGroup::new(4, 16 * 1024, 'g'),
+                SectorGroup::new(1, 64 * 1024, 'g'),
+                SectorGroup::new(7, 128 * 1024, 'g'),
+                SectorGroup::new(4, 16 * 1024, 'g'),
+                SectorGroup::new(1, 64 * 1024, 'g'),
+                SectorGroup::new(7, 128 * 1024, 'g'),
+            ],
+            Self::F74x => vec![
+                SectorGroup::new(4, 32 * 1024, 'g'),
+                SectorGroup::new(1, 128 * 1024, 'g'),
+                SectorGroup::new(3, 256 * 1024, 'g'),
+            ],
+        };
+
+        MemoryLayout::new(
+            String::from("Internal Flash"),
+            vec![MemoryRegion::new(FLASH_ADDRESS, sectors)],
+        )
+    }
+
+    /// Returns all sectors of the internal flash in ascending address order.
+    pub fn sectors(&self) -> Vec<Sector> {
+        let mut sectors = Vec::new();
+
+        for region in self.layout().regions {
+            let mut address = region.address as u64;
+
+            for group in region.sectors {
+                for _ in 0..group.count {
+                    sectors.push(Sector::new(
+                        sectors.len() as u32,
+                        address as u32,
+                        group.sector_size,
+                    ));
+                    address += group.sector_size as u64;
+                }
+            }
+        }
+
+        sectors
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Single flash sector.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Sector {
+    /// Sector number as used by erase commands.
+    pub index: u32,
+
+    /// Start address.
+    pub address: u32,
+
+    /// Size in bytes.
+    pub size: u32,
+}
+
+impl Sector {
+    /// Creates a new sector.
+    pub fn new(index: u32, address: u32, size: u32) -> Self {
+        Self {
+            index,
+            address,
+            size,
+        }
+    }
+
+    /// Returns the address following the end of the sector.
+    pub fn end(&self) -> u64 {
+        self.address as u64 + self.size as u64
+    }
+
+    /// Check if the address range of `size` bytes starting at `address`
+    /// overlaps with the sector.
+    pub fn overlaps(&self, address: u32, size: u32) -> bool {
+        size > 0
+            && (address as u64) < self.end()
+            && address as u64 + size as u64 > self.address as u64
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns the sectors that have to be erased to write all elements
+/// of the image, in ascending address order.
+///
+/// Each sector is returned once, even if it is touched by multiple elements.
+/// Elements outside the internal flash are ignored.
+pub fn sectors_to_erase(family: Family, image: &Image) -> Vec<Sector> {
+    family
+        .sectors()
+        .into_iter()
+        .filter(|sector| {
+            image
+                .image_elements
+                .iter()
+                .any(|element| sector.overlaps(element.dwElementAddress, element.dwElementSize))
+        })
+        .collect()
+}
@@ -9,14 +9,25 @@
 pub mod crc32;
 pub mod dfuse;
 
-use std::io::{Read, Seek};
+use std::io::{Read, Seek, Write};
 
-use anyhow::{anyhow, Result};
+use ::anyhow::anyhow;
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+pub use ::anyhow;
+
+/// Result type returned by all fallible functions of this crate.
+///
+/// The error is an [`anyhow::Error`] that wraps either an [`Error`],
+/// a [`dfuse::Error`] or an I/O error and can be downcast to the
+/// specific type.
+pub type Result<T, E = anyhow::Error> = std::result::Result<T, E>;
 
 ////////////////////////////////////////////////////////////////////////////////
 
 /// File handle
-#[derive(Debug)]
 pub struct DfuFile {
     /// Reference to the file on the filesystem.
     pub file: std::fs::File,
@@ -29,6 +40,29 @@ pub struct DfuFile {
 
     /// The file suffix with meta information.
     pub suffix: Suffix,
+
+    /// Element data cached by [`DfuFile::cache_element`], indexed by
+    /// alternate setting and element index.
+    element_cache: std::collections::HashMap<(u8, usize), Vec<u8>>,
+}
+
+impl std::fmt::Debug for DfuFile {
+    /// Formats the file handle with the element cache shown by the number
+    /// of entries and their total size only.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let cache_size = self.element_cache.values().map(Vec::len).sum::<usize>();
+
+        f.debug_struct("DfuFile")
+            .field("file", &self.file)
+            .field("path", &self.path)
+            .field("content", &self.content)
+            .field("suffix", &self.suffix)
+            .field(
+                "element_cache",
+                &format_args!("{} entries, {} bytes", self.element_cache.len(), cache_size),
+            )
+            .finish()
+    }
 }
 
 impl DfuFile {
@@ -44,69 +78,1082 @@ impl DfuFile {
             path,
             content,
             suffix,
+            element_cache: std::collections::HashMap::new(),
         }
     }
 
     /// Open existing file.
     pub fn open<P: AsRef<std::path::Path> + Clone>(path: P) -> Result<Self> {
+        Self::open_with_options(path, &ParseOptions::default())
+    }
+
+    /// Open existing file using custom parse options.
+    pub fn open_with_options<P: AsRef<std::path::Path> + Clone>(
+        path: P,
+        options: &ParseOptions,
+    ) -> Result<Self> {
+        let file = std::fs::File::open(path.clone())?;
+
+        Self::from_file_with_options(file, std::path::PathBuf::from(path.as_ref()), options)
+    }
+
+    /// Creates a new instance from a file that is already open.
+    ///
+    /// The `path` is stored for reference only, the file is not reopened.
+    /// Methods modifying the file in place, such as [`DfuFile::rewrite_suffix`],
+    /// take a writable handle from the caller instead.
+    pub fn from_file(file: std::fs::File, path: std::path::PathBuf) -> Result<Self> {
+        Self::from_file_with_options(file, path, &ParseOptions::default())
+    }
+
+    /// Creates a new instance from a file that is already open using custom
+    /// parse options.
+    pub fn from_file_with_options(
+        mut file: std::fs::File,
+        path: std::path::PathBuf,
+        options: &ParseOptions,
+    ) -> Result<Self> {
+        let (content, suffix) = Self::parse(&mut file, options)?;
+
+        Ok(Self::new(file, path, content, suffix))
+    }
+
+    /// Create a new DfuSe file at `path` and open it.
+    ///
+    /// The data of all elements must be held in memory. The sizes and counts
+    /// in the headers are recomputed before writing. In the suffix, `bcdDFU`
+    /// is set to 0x011A as required for DfuSe files, `bLength` is set
+    /// according to the vendor data and the CRC is calculated. Vendor data
+    /// of more than 239 bytes results in [`Error::SuffixTooLong`].
+    pub fn create<P: AsRef<std::path::Path> + Clone>(
+        path: P,
+        mut content: dfuse::Content,
+        mut suffix: Suffix,
+    ) -> Result<Self> {
+        content.recompute_sizes()?;
+
+        let mut data = Vec::new();
+        content.write_to(&mut data, |element| {
+            element
+                .data
+                .clone()
+                .ok_or_else(|| anyhow!(dfuse::Error::ElementDataNotLoaded))
+        })?;
+
+        suffix.bcdDFU = 0x011A;
+        suffix.bLength = u8::try_from(suffix.total_length())
+            .map_err(|_| anyhow!(Error::SuffixTooLong(suffix.total_length())))?;
+        let suffix = suffix.with_computed_crc(&data);
+
+        data.extend_from_slice(&suffix.vendor_data);
+        data.extend_from_slice(&suffix.to_bytes());
+        std::fs::write(path.clone(), data)?;
+
+        Self::open(path)
+    }
+
+    /// Open existing file leniently, collecting non-fatal issues as warnings.
+    ///
+    /// In contrast to [`DfuFile::open`], DfuSe content is parsed up to the
+    /// first image that fails to read and the size of a short last element
+    /// is clamped as with [`ParseOptions::allow_short_last_element`].
+    /// All anomalies found in the file are returned along with it.
+    pub fn open_with_warnings<P: AsRef<std::path::Path> + Clone>(
+        path: P,
+    ) -> Result<(Self, Vec<Warning>)> {
         let mut file = std::fs::File::open(path.clone())?;
+        let mut warnings = Vec::new();
 
         let file_size = file.seek(std::io::SeekFrom::End(0))?;
 
         // File must be at least as large as the suffix
-        if file_size < SUFFIX_LENGTH as u64 {
+        if file_size < Suffix::LENGTH as u64 {
             return Err(anyhow!(Error::InsufficientFileSize));
         }
 
-        let content = if dfuse::detect(&mut file)? {
-            Content::DfuSe(dfuse::Content::from_file(&mut file)?)
+        let content = if dfuse::detect(&mut file)?.is_dfuse()? {
+            let options = ParseOptions {
+                allow_short_last_element: true,
+                ..Default::default()
+            };
+
+            let (content, error) =
+                dfuse::Content::parse(&mut file, &options, true, &mut |event| {
+                    if let dfuse::ParseEvent::ElementClamped { declared_size, .. } = event {
+                        warnings.push(Warning::LastElementClamped { declared_size });
+                    }
+                })?;
+
+            if let Some(error) = error {
+                warnings.push(Warning::IncompleteContent(error.to_string()));
+            }
+
+            Content::DfuSe(content)
         } else {
             Content::Plain
         };
 
         let suffix = Suffix::from_file(&mut file)?;
 
-        Ok(Self::new(
+        let mut dfu_file = Self::new(
             file,
             std::path::PathBuf::from(path.as_ref()),
             content,
             suffix,
+        );
+        warnings.extend(dfu_file.collect_warnings()?);
+
+        Ok((dfu_file, warnings))
+    }
+
+    /// Check the file for consistency and gather the results in a report.
+    ///
+    /// All checks are run, so the report covers every issue found
+    /// instead of stopping at the first one.
+    pub fn analyze(&mut self) -> Result<AnalysisReport> {
+        let crc = self.calc_crc()?;
+        let mut report = AnalysisReport {
+            crc_valid: crc == self.suffix.dwCRC,
+            crc,
+            declared_size_matches: true,
+            ..Default::default()
+        };
+
+        let content = match &self.content {
+            Content::DfuSe(content) => content,
+            Content::Plain => return Ok(report),
+        };
+
+        report.declared_size_matches = content.prefix.DFUImageSize as u64 == content.images_end();
+        report.duplicate_alts = content.find_duplicate_alt_settings();
+
+        let file_size = self.file.metadata()?.len() - self.suffix.vendor_data.len() as u64;
+        report.missing_bytes = content.computed_file_size().saturating_sub(file_size);
+        report.trailing_gap = content.trailing_gap(file_size);
+
+        for (image_index, image) in content.images.iter().enumerate() {
+            let alt_setting = image.target_prefix.bAlternateSetting;
+            let element_meta = |element_index: usize| {
+                let element = &image.image_elements[element_index];
+
+                dfuse::ElementMeta {
+                    image_index,
+                    element_index,
+                    alt_setting,
+                    address: element.dwElementAddress,
+                    size: element.dwElementSize,
+                }
+            };
+
+            for (index, other_index) in image.find_overlaps() {
+                report
+                    .overlaps
+                    .push((element_meta(index), element_meta(other_index)));
+            }
+
+            report.images.push(ImageSummary {
+                alt_setting,
+                name: image.target_prefix.szTargetName.clone(),
+                element_count: image.image_elements.len(),
+                declared_size: image.target_prefix.dwTargetSize,
+                computed_size: image.elements_size(),
+                empty_elements: image
+                    .image_elements
+                    .iter()
+                    .filter(|element| element.dwElementSize == 0)
+                    .map(|element| element.dwElementAddress)
+                    .collect(),
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Check the file for non-fatal issues.
+    fn collect_warnings(&mut self) -> Result<Vec<Warning>> {
+        let mut warnings = Vec::new();
+
+        let crc = self.calc_crc()?;
+
+        if crc != self.suffix.dwCRC {
+            warnings.push(Warning::CrcMismatch {
+                stored: self.suffix.dwCRC,
+                calculated: crc,
+            });
+        }
+
+        let content = match &self.content {
+            Content::DfuSe(content) => content,
+            Content::Plain => {
+                if !self.suffix.is_plain_dfu_revision() {
+                    warnings.push(Warning::UnknownDfuSpecNo(self.suffix.bcdDFU));
+                }
+
+                return Ok(warnings);
+            }
+        };
+
+        let file_size = self.file.seek(std::io::SeekFrom::End(0))?;
+
+        let images_end = content.images_end();
+
+        if content.prefix.DFUImageSize as u64 != images_end {
+            warnings.push(Warning::ImageSizeMismatch {
+                declared: content.prefix.DFUImageSize,
+                computed: images_end,
+            });
+        }
+
+        for alt_setting in content.find_duplicate_alt_settings() {
+            warnings.push(Warning::DuplicateAltSetting(alt_setting));
+        }
+
+        let mut target_pos = dfuse::Prefix::LENGTH as u64;
+
+        for image in content.images.iter() {
+            let alt_setting = image.target_prefix.bAlternateSetting;
+
+            self.file.seek(std::io::SeekFrom::Start(target_pos))?;
+            let mut buffer = [0; dfuse::TargetPrefix::LENGTH];
+            self.file.read_exact(&mut buffer)?;
+
+            // Bytes following the name in the name field should be zero
+            let name_field = &buffer[11..266];
+            let name_len = if image.target_prefix.is_named() {
+                name_field
+                    .iter()
+                    .position(|byte| *byte == 0)
+                    .unwrap_or(name_field.len())
+            } else {
+                0
+            };
+
+            if name_field[name_len..].iter().any(|byte| *byte != 0) {
+                warnings.push(Warning::TargetNameGarbage(alt_setting));
+            }
+
+            // A name filling the whole field may have been truncated
+            if name_len == name_field.len() {
+                warnings.push(Warning::TargetNameUnterminated(alt_setting));
+            }
+
+            let elements_size = image.elements_size();
+
+            if image.target_prefix.dwTargetSize as u64 != elements_size {
+                warnings.push(Warning::TargetSizeMismatch {
+                    alt_setting,
+                    declared: image.target_prefix.dwTargetSize,
+                    computed: elements_size,
+                });
+            }
+
+            for element in image.image_elements.iter() {
+                if element.dwElementSize == 0 {
+                    warnings.push(Warning::EmptyElement {
+                        alt_setting,
+                        address: element.dwElementAddress,
+                    });
+                }
+
+                if !element.verify_data_present(&mut self.file)? {
+                    warnings.push(Warning::ElementDataMissing {
+                        alt_setting,
+                        address: element.dwElementAddress,
+                    });
+                }
+            }
+
+            target_pos += dfuse::TargetPrefix::LENGTH as u64 + elements_size;
+        }
+
+        let trailing_gap = content.trailing_gap(file_size - self.suffix.vendor_data.len() as u64);
+
+        if trailing_gap > 0 {
+            warnings.push(Warning::TrailingGap(trailing_gap));
+        }
+
+        Ok(warnings)
+    }
+
+    /// Parse a DFU file from a buffer in memory.
+    ///
+    /// The buffer is retained, so element data can be accessed without
+    /// any further I/O.
+    pub fn from_bytes(data: Vec<u8>) -> Result<InMemoryDfu> {
+        let mut cursor = std::io::Cursor::new(data);
+        let (content, suffix) = Self::parse(&mut cursor, &ParseOptions::default())?;
+
+        Ok(InMemoryDfu::new(cursor.into_inner(), content, suffix))
+    }
+
+    /// Open a gzip-compressed file.
+    ///
+    /// The file is decompressed into memory completely before parsing.
+    #[cfg(feature = "flate2")]
+    pub fn open_gz<P: AsRef<std::path::Path>>(path: P) -> Result<InMemoryDfu> {
+        let file = std::fs::File::open(path)?;
+        let mut data = Vec::new();
+        flate2::read::GzDecoder::new(file).read_to_end(&mut data)?;
+
+        Self::from_bytes(data)
+    }
+
+    /// Parse content and suffix from a reader.
+    fn parse<R: Read + Seek>(file: &mut R, options: &ParseOptions) -> Result<(Content, Suffix)> {
+        let file_size = file.seek(std::io::SeekFrom::End(0))?;
+
+        // File must be at least as large as the suffix
+        if file_size < Suffix::LENGTH as u64 {
+            return Err(anyhow!(Error::InsufficientFileSize));
+        }
+
+        let content = if dfuse::detect(file)?.is_dfuse()? {
+            Content::DfuSe(dfuse::Content::from_file_with_options(file, options)?)
+        } else {
+            Content::Plain
+        };
+
+        let suffix = Suffix::from_file(file)?;
+
+        Ok((content, suffix))
+    }
+
+    /// Open existing file asynchronously.
+    #[cfg(feature = "tokio")]
+    pub async fn open_async<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        Self::open_async_with_options(path, &ParseOptions::default()).await
+    }
+
+    /// Open existing file asynchronously using custom parse options.
+    #[cfg(feature = "tokio")]
+    pub async fn open_async_with_options<P: AsRef<std::path::Path>>(
+        path: P,
+        options: &ParseOptions,
+    ) -> Result<Self> {
+        let mut file = tokio::fs::File::open(path.as_ref()).await?;
+
+        let file_size = file.seek(std::io::SeekFrom::End(0)).await?;
+
+        // File must be at least as large as the suffix
+        if file_size < Suffix::LENGTH as u64 {
+            return Err(anyhow!(Error::InsufficientFileSize));
+        }
+
+        let content = if dfuse::detect_async(&mut file).await?.is_dfuse()? {
+            Content::DfuSe(dfuse::Content::from_file_async_with_options(&mut file, options).await?)
+        } else {
+            Content::Plain
+        };
+
+        let suffix = Suffix::from_file_async(&mut file).await?;
+
+        Ok(Self::new(
+            file.into_std().await,
+            std::path::PathBuf::from(path.as_ref()),
+            content,
+            suffix,
         ))
     }
 
     /// Calculate the CRC32 checksum of whole file excluding the last 4 bytes,
     /// which contain the checksum itself.
+    ///
+    /// Vendor data of an extended suffix is part of the checksummed region.
     pub fn calc_crc(&mut self) -> Result<u32> {
+        calc_file_crc(&mut self.file)
+    }
+
+    /// Read the region of the file covered by the CRC, which is everything
+    /// except the last 4 bytes containing the checksum itself.
+    pub fn signed_region(&mut self) -> Result<Vec<u8>> {
         let file_size = self.file.seek(std::io::SeekFrom::End(0))?;
         self.file.rewind()?;
 
-        const CHUNK_SIZE: u64 = 1024;
-        let mut file_pos = 0;
-        let mut crc = 0;
+        let mut buffer = vec![0; (file_size - 4) as usize];
+        self.file.read_exact(&mut buffer)?;
 
-        loop {
-            let read_size = std::cmp::min(CHUNK_SIZE, file_size - 4 - file_pos);
+        Ok(buffer)
+    }
+
+    /// Returns the offset of the suffix from the start of the file,
+    /// including the vendor data of an extended suffix.
+    pub fn suffix_offset(&self) -> Result<u64> {
+        let file_size = self.file.metadata()?.len();
+
+        Ok(file_size - self.suffix.total_length() as u64)
+    }
 
-            if read_size == 0 {
-                break;
+    /// Overwrite the standard suffix fields of the file through `out`,
+    /// leaving the rest of the file untouched.
+    ///
+    /// The file is not reopened, so `out` must be a writable handle to the
+    /// same file, e.g. opened with [`std::fs::OpenOptions`]. All fields of
+    /// [`DfuFile::suffix`] except `dwCRC` are written. The CRC is calculated
+    /// from the unchanged part of the file and the new fields and updated in
+    /// [`DfuFile::suffix`] as well, so the file is consistent afterwards.
+    pub fn rewrite_suffix<W: Write + Seek>(&mut self, out: &mut W) -> Result<()> {
+        let offset = self.suffix_offset()? + self.suffix.vendor_data.len() as u64;
+        let buffer = self.suffix.to_bytes();
+
+        let mut crc_writer = CrcWriter::new(std::io::sink());
+        self.file.rewind()?;
+        copy_exact(&mut self.file, &mut crc_writer, offset)?;
+        crc_writer.write_all(&buffer[..Suffix::LENGTH - 4])?;
+        self.suffix.dwCRC = crc_writer.crc.finalize() ^ 0xFFFFFFFF_u32;
+
+        out.seek(std::io::SeekFrom::Start(offset))?;
+        out.write_all(&buffer[..Suffix::LENGTH - 4])?;
+        out.write_all(&self.suffix.dwCRC.to_le_bytes())?;
+        out.flush()?;
+
+        Ok(())
+    }
+
+    /// Write the file contents without the suffix to a new file at `out`.
+    ///
+    /// For plain content, this is the original firmware. For DfuSe content,
+    /// it is the raw DfuSe image including the prefix, target prefixes and
+    /// element headers, not a flat memory image. Use [`dfuse::Image::to_flat_binary`]
+    /// or [`dfuse::ImageElement::read_all`] to get the memory contents instead.
+    ///
+    /// Returns the number of bytes written.
+    pub fn strip_suffix_to<P: AsRef<std::path::Path>>(&mut self, out: P) -> Result<u64> {
+        let size = self.suffix_offset()?;
+        self.file.rewind()?;
+
+        let mut out_file = std::fs::File::create(out)?;
+        copy_exact(&mut self.file, &mut out_file, size)?;
+        out_file.flush()?;
+
+        Ok(size)
+    }
+
+    /// Write the file to a new file at `out`, streaming the element data.
+    ///
+    /// Element data not held in memory is copied chunk by chunk from the
+    /// original file, so the memory usage does not depend on the file size.
+    /// The headers are written from the parsed content and the suffix CRC
+    /// is recalculated while writing. As with [`dfuse::Content::write_to`],
+    /// sizes are written as they are, so [`dfuse::Content::recompute_sizes`]
+    /// should be called after any modifications.
+    ///
+    /// Returns the number of bytes written, which is the size of the new file.
+    pub fn write_streaming<P: AsRef<std::path::Path>>(&mut self, out: P) -> Result<u64> {
+        self.write_streaming_with_options(out, &WriteOptions::default())
+    }
+
+    /// Write the file to a new file at `out` using custom write options,
+    /// streaming the element data.
+    ///
+    /// With [`WriteOptions::preserve_trailing_data`] set, the bytes between
+    /// the end of the images and the suffix are copied from the original
+    /// file, see [`dfuse::Content::trailing_gap`]. Their position is derived
+    /// from the current content, so this is only meaningful if the element
+    /// sizes are unchanged.
+    ///
+    /// Returns the number of bytes written, which is the size of the new file.
+    pub fn write_streaming_with_options<P: AsRef<std::path::Path>>(
+        &mut self,
+        out: P,
+        options: &WriteOptions,
+    ) -> Result<u64> {
+        let mut writer = CrcWriter::new(std::io::BufWriter::new(std::fs::File::create(out)?));
+
+        match &self.content {
+            Content::Plain => {
+                let payload_size = self.plain_payload_size()?;
+                self.file.rewind()?;
+                copy_exact(&mut self.file, &mut writer, payload_size)?;
             }
+            Content::DfuSe(content) => {
+                writer.write_all(&content.prefix.to_bytes())?;
 
-            let mut buffer = vec![0; read_size as usize];
-            self.file.read_exact(&mut buffer)?;
+                for image in content.images.iter() {
+                    writer.write_all(&image.target_prefix.to_bytes())?;
+
+                    for element in image.image_elements.iter() {
+                        writer.write_all(&element.to_bytes())?;
+
+                        match &element.data {
+                            Some(data) => {
+                                if data.len() != element.dwElementSize as usize {
+                                    return Err(anyhow!(Error::LengthMismatch {
+                                        expected: element.dwElementSize as usize,
+                                        actual: data.len(),
+                                    }));
+                                }
+
+                                writer.write_all(data)?;
+                            }
+                            None => {
+                                self.file
+                                    .seek(std::io::SeekFrom::Start(element.data_position))?;
+                                copy_exact(
+                                    &mut self.file,
+                                    &mut writer,
+                                    element.dwElementSize as u64,
+                                )?;
+                            }
+                        }
+                    }
+                }
+
+                if options.preserve_trailing_data {
+                    let suffix_offset = self.suffix_offset()?;
+                    let images_end = content.images_end();
+
+                    if suffix_offset > images_end {
+                        self.file.seek(std::io::SeekFrom::Start(images_end))?;
+                        copy_exact(&mut self.file, &mut writer, suffix_offset - images_end)?;
+                    }
+                }
+            }
+        }
+
+        let buffer = self.suffix.to_bytes();
+        writer.write_all(&self.suffix.vendor_data)?;
+        writer.write_all(&buffer[..Suffix::LENGTH - 4])?;
+
+        let crc = writer.crc.clone().finalize() ^ 0xFFFFFFFF_u32;
+        writer.write_all(&crc.to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(writer.written)
+    }
+
+    /// Read the data of the element at `index` in the image with
+    /// alternate setting `alt_setting` and keep it in a cache.
+    ///
+    /// The file is only read on the first call for an element, subsequent
+    /// calls return the cached data. The cache is not updated when the
+    /// content is modified and keeps growing until it is cleared with
+    /// [`DfuFile::clear_element_cache`].
+    pub fn cache_element(&mut self, alt_setting: u8, index: usize) -> Result<&[u8]> {
+        let key = (alt_setting, index);
+
+        if !self.element_cache.contains_key(&key) {
+            let content = match &self.content {
+                Content::DfuSe(content) => content,
+                Content::Plain => return Err(anyhow!(Error::NotDfuSeContent)),
+            };
+
+            let element = content
+                .find_image_by_alt(alt_setting)
+                .ok_or_else(|| anyhow!(Error::UnknownAltSetting(alt_setting)))?
+                .image_elements
+                .get(index)
+                .ok_or_else(|| anyhow!(dfuse::Error::InvalidElementIndex))?;
+
+            let data = element.read_all(&mut self.file)?;
+            self.element_cache.insert(key, data);
+        }
+
+        Ok(&self.element_cache[&key])
+    }
+
+    /// Remove all element data from the cache.
+    pub fn clear_element_cache(&mut self) {
+        self.element_cache.clear();
+    }
+
+    /// Returns the size of the payload of a plain file in bytes.
+    ///
+    /// The payload is the data from the start of the file up to the suffix.
+    pub fn plain_payload_size(&mut self) -> Result<u64> {
+        if !matches!(self.content, Content::Plain) {
+            return Err(anyhow!(Error::NotPlainContent));
+        }
+
+        let file_size = self.file.seek(std::io::SeekFrom::End(0))?;
+
+        Ok(file_size - self.suffix.total_length() as u64)
+    }
+
+    /// Read the whole payload of a plain file.
+    pub fn plain_payload(&mut self) -> Result<Vec<u8>> {
+        let payload_size = self.plain_payload_size()?;
+        self.file.rewind()?;
+
+        let mut buffer = vec![0; payload_size as usize];
+        self.file.read_exact(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    /// Returns a reader for the payload of a plain file.
+    ///
+    /// The reader starts at the beginning of the file and stops right before
+    /// the suffix. DfuSe files have no contiguous payload and return an error.
+    pub fn payload_reader(&mut self) -> Result<impl Read + '_> {
+        let payload_size = self.plain_payload_size()?;
+        self.file.rewind()?;
 
-            crc = crc32::crc32(&buffer, crc);
+        Ok((&mut self.file).take(payload_size))
+    }
+
+    /// Read payload data of a plain file into a buffer.
+    ///
+    /// The `position` argument is relative to the start of the payload.
+    /// The function returns the number of valid bytes in the buffer. This may
+    /// be less than the buffer size when reaching the end of the payload,
+    /// so it can be called repeatedly to process the payload in chunks.
+    pub fn read_plain_payload_at(&mut self, position: u64, buffer: &mut [u8]) -> Result<usize> {
+        let payload_size = self.plain_payload_size()?;
 
-            file_pos += read_size;
+        if position >= payload_size {
+            return Ok(0);
         }
 
-        Ok(crc ^ 0xFFFFFFFF_u32)
+        let read_size = std::cmp::min(buffer.len() as u64, payload_size - position) as usize;
+        self.file.seek(std::io::SeekFrom::Start(position))?;
+        let read_size = self.file.read(&mut buffer[..read_size])?;
+
+        Ok(read_size)
+    }
+
+    /// Convert the payload of a plain file into DfuSe content.
+    ///
+    /// The content consists of a single image for the alternate setting `alt`
+    /// with a single element placed at `address`. The element data is held
+    /// in memory. The name is rejected under the same conditions as with
+    /// [`dfuse::TargetPrefix::set_name`].
+    pub fn plain_to_dfuse(&mut self, alt: u8, name: &str, address: u32) -> Result<dfuse::Content> {
+        let mut target_prefix = dfuse::TargetPrefix {
+            bAlternateSetting: alt,
+            ..Default::default()
+        };
+        target_prefix.set_name(name)?;
+
+        let payload = self.plain_payload()?;
+
+        let image_element = dfuse::ImageElement::with_data(address, payload)?;
+
+        let image = dfuse::Image::new(target_prefix, vec![image_element]);
+        let mut content = dfuse::Content::new(dfuse::Prefix::default(), vec![image]);
+        content.recompute_sizes()?;
+
+        Ok(content)
+    }
+
+    /// Check if both files contain the same firmware.
+    ///
+    /// Plain files are compared by their payload. DfuSe files are compared
+    /// by the alternate settings of their images and the addresses and data
+    /// of the elements. The suffix fields, the DfuSe format version and the
+    /// target names are ignored, so files differing only in metadata like
+    /// the device version are considered the same.
+    pub fn same_payload_as(&mut self, other: &mut DfuFile) -> Result<bool> {
+        match (&self.content, &other.content) {
+            (Content::Plain, Content::Plain) => {
+                if self.plain_payload_size()? != other.plain_payload_size()? {
+                    return Ok(false);
+                }
+
+                Ok(self.plain_payload()? == other.plain_payload()?)
+            }
+            (Content::DfuSe(content), Content::DfuSe(other_content)) => {
+                if content.images.len() != other_content.images.len() {
+                    return Ok(false);
+                }
+
+                for (image, other_image) in content.images.iter().zip(&other_content.images) {
+                    if image.target_prefix.bAlternateSetting
+                        != other_image.target_prefix.bAlternateSetting
+                        || image.image_elements.len() != other_image.image_elements.len()
+                    {
+                        return Ok(false);
+                    }
+
+                    for (element, other_element) in
+                        image.image_elements.iter().zip(&other_image.image_elements)
+                    {
+                        if element.dwElementAddress != other_element.dwElementAddress
+                            || element.dwElementSize != other_element.dwElementSize
+                        {
+                            return Ok(false);
+                        }
+
+                        if element.read_all(&mut self.file)?
+                            != other_element.read_all(&mut other.file)?
+                        {
+                            return Ok(false);
+                        }
+                    }
+                }
+
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
 
-/// File content variants.
+/// Classify a file by its prefix and suffix signatures.
+///
+/// Only the first 5 and the last 16 bytes of the file are read, so malformed
+/// images or an invalid CRC are not detected.
+pub fn sniff<P: AsRef<std::path::Path>>(path: P) -> Result<FileKind> {
+    let mut file = std::fs::File::open(path)?;
+
+    let file_size = file.seek(std::io::SeekFrom::End(0))?;
+
+    if file_size < Suffix::LENGTH as u64 {
+        return Ok(FileKind::NotDfu);
+    }
+
+    match dfuse::detect(&mut file)? {
+        dfuse::Detection::DfuSe => return Ok(FileKind::DfuSe),
+        dfuse::Detection::InvalidSuffix => return Ok(FileKind::InvalidDfuSe),
+        dfuse::Detection::NotDfuSe => {}
+    }
+
+    file.seek(std::io::SeekFrom::End(-(Suffix::LENGTH as i64)))?;
+    let mut buffer = [0; Suffix::LENGTH];
+    file.read_exact(&mut buffer)?;
+
+    if Suffix::from_bytes(&buffer).is_signature_valid() {
+        Ok(FileKind::Plain)
+    } else {
+        Ok(FileKind::NotDfu)
+    }
+}
+
+/// File kinds distinguished by [`sniff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    /// Standard DFU file with raw content.
+    Plain,
+
+    /// DfuSe file with extensions from STMicroelectronics.
+    DfuSe,
+
+    /// File with a DfuSe prefix, but a suffix that is invalid or does not
+    /// declare the DfuSe version, see [`dfuse::Detection::InvalidSuffix`].
+    InvalidDfuSe,
+
+    /// File without a valid DFU suffix.
+    NotDfu,
+}
+
+/// Check if the CRC32 stored in the suffix of a file is valid.
+///
+/// Only the checksum is verified, the file is not parsed otherwise.
+pub fn verify_file_crc<P: AsRef<std::path::Path>>(path: P) -> Result<bool> {
+    let mut file = std::fs::File::open(path)?;
+
+    let file_size = file.seek(std::io::SeekFrom::End(0))?;
+
+    // File must be at least as large as the suffix
+    if file_size < Suffix::LENGTH as u64 {
+        return Err(anyhow!(Error::InsufficientFileSize));
+    }
+
+    file.seek(std::io::SeekFrom::End(-4))?;
+    let mut buffer = [0; 4];
+    file.read_exact(&mut buffer)?;
+
+    Ok(calc_file_crc(&mut file)? == u32::from_le_bytes(buffer))
+}
+
+/// Calculate the CRC32 of a file and write it into the suffix in place.
+///
+/// Only the last 4 bytes of the file are changed. The new CRC is returned.
+pub fn fix_crc<P: AsRef<std::path::Path>>(path: P) -> Result<u32> {
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)?;
+
+    let file_size = file.seek(std::io::SeekFrom::End(0))?;
+
+    // File must be at least as large as the suffix
+    if file_size < Suffix::LENGTH as u64 {
+        return Err(anyhow!(Error::InsufficientFileSize));
+    }
+
+    let crc = calc_file_crc(&mut file)?;
+
+    file.seek(std::io::SeekFrom::End(-4))?;
+    file.write_all(&crc.to_le_bytes())?;
+    file.flush()?;
+
+    Ok(crc)
+}
+
+/// Parse an archive of concatenated DFU files.
+///
+/// The archive is read file by file, so apart from the returned files only
+/// the file being parsed and a limited lookahead are held in memory. Each
+/// [`InMemoryDfu`] owns the data of its file, so the memory usage is about
+/// the size of the archive.
+///
+/// DfuSe files are bounded by the image size in their prefix and the length
+/// of their suffix. Plain files contain no size information, so the archive
+/// is scanned for the first suffix with a matching CRC, reading ahead in
+/// growing chunks. Parsing stops at the end of the archive, an incomplete
+/// file at the end results in an error.
+pub fn parse_multi<R: Read + Seek>(reader: &mut R) -> Result<Vec<InMemoryDfu>> {
+    let archive_size = reader.seek(std::io::SeekFrom::End(0))?;
+
+    let mut files = Vec::new();
+    let mut offset = 0;
+
+    while offset < archive_size {
+        reader.seek(std::io::SeekFrom::Start(offset))?;
+        let data = read_archive_entry(reader)?
+            .ok_or_else(|| anyhow!(Error::TruncatedArchive { offset }))?;
+
+        offset += data.len() as u64;
+        files.push(DfuFile::from_bytes(data)?);
+    }
+
+    Ok(files)
+}
+
+/// Read the DFU file at the current position of an archive
+/// or return `None` if no complete file is found.
+fn read_archive_entry<R: Read>(reader: &mut R) -> Result<Option<Vec<u8>>> {
+    const CHUNK_SIZE: u64 = 4096;
+
+    let mut buffer = Vec::new();
+    reader
+        .by_ref()
+        .take(dfuse::Prefix::LENGTH as u64)
+        .read_to_end(&mut buffer)?;
+
+    let is_dfuse = buffer.starts_with(b"DfuSe") && buffer.len() == dfuse::Prefix::LENGTH;
+
+    // DfuSe files end within the longest possible suffix after the image
+    let mut read_size = if is_dfuse {
+        let image_size = u32::from_le_bytes([buffer[6], buffer[7], buffer[8], buffer[9]]);
+        image_size as u64 + u8::MAX as u64 - buffer.len() as u64
+    } else {
+        CHUNK_SIZE
+    };
+
+    loop {
+        let read = reader.by_ref().take(read_size).read_to_end(&mut buffer)?;
+
+        if let Some(length) = archive_entry_length(&buffer) {
+            buffer.truncate(length);
+            return Ok(Some(buffer));
+        }
+
+        if is_dfuse || (read as u64) < read_size {
+            return Ok(None);
+        }
+
+        // Doubling the buffer keeps the effort of repeated scans linear
+        read_size = buffer.len() as u64;
+    }
+}
+
+/// Returns the length of the DFU file at the start of an archive buffer
+/// or `None` if no complete file is found.
+fn archive_entry_length(buffer: &[u8]) -> Option<usize> {
+    // Suffix must have a valid signature and length
+    let suffix_end = |end: usize| {
+        let suffix = buffer.get(end.checked_sub(Suffix::LENGTH)?..end)?;
+        (&suffix[8..11] == b"UFD" && suffix[11] as usize >= Suffix::LENGTH).then(|| suffix[11])
+    };
+
+    if buffer.starts_with(b"DfuSe") && buffer.len() >= dfuse::Prefix::LENGTH {
+        let image_size = u32::from_le_bytes([buffer[6], buffer[7], buffer[8], buffer[9]]) as usize;
+
+        // Vendor data of an extended suffix precedes the standard fields
+        return (Suffix::LENGTH..=u8::MAX as usize)
+            .map(|length| image_size + length)
+            .find(|&end| suffix_end(end) == Some((end - image_size) as u8));
+    }
+
+    let mut crc = crc32::Crc32::new();
+
+    for end in Suffix::LENGTH..=buffer.len() {
+        crc.update(&buffer[end - Suffix::LENGTH..end - Suffix::LENGTH + 1]);
+
+        if suffix_end(end).is_none() {
+            continue;
+        }
+
+        // Extend the CRC over the suffix except the stored CRC itself
+        let mut suffix_crc = crc.clone();
+        suffix_crc.update(&buffer[end - Suffix::LENGTH + 1..end - 4]);
+        let stored = u32::from_le_bytes([
+            buffer[end - 4],
+            buffer[end - 3],
+            buffer[end - 2],
+            buffer[end - 1],
+        ]);
+
+        if suffix_crc.finalize() ^ 0xFFFFFFFF_u32 == stored {
+            return Some(end);
+        }
+    }
+
+    None
+}
+
+/// Calculate the CRC32 checksum of a whole file excluding the last 4 bytes.
+fn calc_file_crc<R: Read + Seek>(file: &mut R) -> Result<u32> {
+    let file_size = file.seek(std::io::SeekFrom::End(0))?;
+    file.rewind()?;
+
+    const CHUNK_SIZE: u64 = 1024;
+    let mut file_pos = 0;
+    let mut crc = crc32::Crc32::new();
+
+    loop {
+        let read_size = std::cmp::min(CHUNK_SIZE, file_size - 4 - file_pos);
+
+        if read_size == 0 {
+            break;
+        }
+
+        let mut buffer = vec![0; read_size as usize];
+        file.read_exact(&mut buffer)?;
+
+        crc.update(&buffer);
+
+        file_pos += read_size;
+    }
+
+    Ok(crc.finalize() ^ 0xFFFFFFFF_u32)
+}
+
+/// Copy exactly `size` bytes from a reader to a writer.
+fn copy_exact<R: Read, W: Write>(reader: &mut R, writer: &mut W, size: u64) -> Result<()> {
+    let copied = std::io::copy(&mut reader.take(size), writer)?;
+
+    if copied != size {
+        return Err(anyhow!(Error::LengthMismatch {
+            expected: size as usize,
+            actual: copied as usize,
+        }));
+    }
+
+    Ok(())
+}
+
+/// Writer calculating the CRC32 of all data written through it.
+struct CrcWriter<W: Write> {
+    /// Underlying writer.
+    inner: W,
+
+    /// CRC32 of the data written so far.
+    crc: crc32::Crc32,
+
+    /// Number of bytes written so far.
+    written: u64,
+}
+
+impl<W: Write> CrcWriter<W> {
+    /// Creates a new instance.
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            crc: crc32::Crc32::new(),
+            written: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for CrcWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let size = self.inner.write(buf)?;
+        self.crc.update(&buf[..size]);
+        self.written += size as u64;
+
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// DFU file held completely in memory.
 #[derive(Debug)]
+pub struct InMemoryDfu {
+    /// Raw file data including the suffix.
+    pub data: Vec<u8>,
+
+    /// The content representation.
+    pub content: Content,
+
+    /// The file suffix with meta information.
+    pub suffix: Suffix,
+}
+
+impl InMemoryDfu {
+    /// Creates a new instance.
+    pub fn new(data: Vec<u8>, content: Content, suffix: Suffix) -> Self {
+        Self {
+            data,
+            content,
+            suffix,
+        }
+    }
+
+    /// Calculate the CRC32 checksum of the whole data excluding the last
+    /// 4 bytes, which contain the checksum itself.
+    ///
+    /// Fails if `data` is shorter than these 4 bytes.
+    pub fn calc_crc(&self) -> Result<u32> {
+        let signed_size = self
+            .data
+            .len()
+            .checked_sub(4)
+            .ok_or_else(|| anyhow!(Error::InsufficientFileSize))?;
+
+        let mut crc = crc32::Crc32::new();
+        crc.update(&self.data[..signed_size]);
+
+        Ok(crc.finalize() ^ 0xFFFFFFFF_u32)
+    }
+
+    /// Returns the payload of a plain file, which is the data up to the suffix.
+    pub fn plain_payload(&self) -> Result<&[u8]> {
+        if !matches!(self.content, Content::Plain) {
+            return Err(anyhow!(Error::NotPlainContent));
+        }
+
+        let payload_size = self
+            .data
+            .len()
+            .checked_sub(self.suffix.total_length())
+            .ok_or_else(|| anyhow!(Error::InsufficientFileSize))?;
+
+        Ok(&self.data[..payload_size])
+    }
+
+    /// Returns the data of an image element.
+    ///
+    /// Data held by the element itself takes precedence. Otherwise, the data
+    /// is sliced from the buffer and may be shorter than `dwElementSize`
+    /// if the buffer ends early.
+    pub fn element_data<'a>(&'a self, element: &'a dfuse::ImageElement) -> &'a [u8] {
+        if let Some(data) = &element.data {
+            return data;
+        }
+
+        let start = std::cmp::min(element.data_position, self.data.len() as u64) as usize;
+        let end = std::cmp::min(
+            element.data_position + element.dwElementSize as u64,
+            self.data.len() as u64,
+        ) as usize;
+
+        &self.data[start..end]
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// File content variants.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Content {
     /// Standard file with raw content.
     Plain,
@@ -115,6 +1162,27 @@ pub enum Content {
     DfuSe(dfuse::Content),
 }
 
+impl Content {
+    /// Returns the DFU specification number the suffix must contain,
+    /// 0x011A for DfuSe content and 0x0100 for plain content.
+    pub fn dfu_spec_no(&self) -> u16 {
+        match self {
+            Self::Plain => 0x0100,
+            Self::DfuSe(_) => 0x011A,
+        }
+    }
+
+    /// Returns a suffix with default values and the DFU specification number
+    /// matching the content.
+    ///
+    /// Using it as base for new files prevents self-inconsistent files,
+    /// e.g. DfuSe content that is not detected as such due to a wrong
+    /// specification number in the suffix.
+    pub fn expected_suffix(&self) -> Suffix {
+        SuffixBuilder::new().dfu_spec_no(self.dfu_spec_no()).build()
+    }
+}
+
 impl std::fmt::Display for Content {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
@@ -130,12 +1198,249 @@ impl std::fmt::Display for Content {
 
 ////////////////////////////////////////////////////////////////////////////////
 
+/// Results of the consistency checks, see [`DfuFile::analyze`].
+///
+/// For plain files, only the CRC is checked and all DfuSe specific
+/// fields are left empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnalysisReport {
+    /// Stored CRC matches the calculated one.
+    pub crc_valid: bool,
+
+    /// Calculated CRC.
+    pub crc: u32,
+
+    /// `DFUImageSize` in the prefix matches the size computed from the images.
+    pub declared_size_matches: bool,
+
+    /// Alternate settings used by more than one image.
+    pub duplicate_alts: Vec<u8>,
+
+    /// Pairs of elements inside the same image with overlapping address ranges.
+    pub overlaps: Vec<(dfuse::ElementMeta, dfuse::ElementMeta)>,
+
+    /// Number of bytes the file is shorter than required by the headers.
+    pub missing_bytes: u64,
+
+    /// Number of bytes between the last image element and the suffix.
+    pub trailing_gap: u64,
+
+    /// Summary of each image in file order.
+    pub images: Vec<ImageSummary>,
+}
+
+/// Summary of a DfuSe image in an [`AnalysisReport`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageSummary {
+    /// Alternate setting.
+    pub alt_setting: u8,
+
+    /// Target name.
+    pub name: String,
+
+    /// Number of elements.
+    pub element_count: usize,
+
+    /// Target size declared in the target prefix.
+    pub declared_size: u32,
+
+    /// Target size computed from the elements.
+    pub computed_size: u64,
+
+    /// Start addresses of elements with a size of zero.
+    pub empty_elements: Vec<u32>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Non-fatal issues found in a file, see [`DfuFile::open_with_warnings`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// Stored CRC does not match the calculated one.
+    CrcMismatch {
+        /// CRC stored in the suffix.
+        stored: u32,
+
+        /// CRC calculated from the file.
+        calculated: u32,
+    },
+
+    /// Parsing of the DfuSe images stopped early due to the given error.
+    IncompleteContent(String),
+
+    /// Image size in the prefix does not match the size computed from
+    /// the elements.
+    ImageSizeMismatch {
+        /// Size declared in the prefix.
+        declared: u32,
+
+        /// Size computed from the elements.
+        computed: u64,
+    },
+
+    /// Alternate setting is used by multiple images.
+    DuplicateAltSetting(u8),
+
+    /// Name field of the target with the given alternate setting contains
+    /// non-zero bytes after the name.
+    TargetNameGarbage(u8),
+
+    /// Name field of the target with the given alternate setting contains
+    /// no null terminator, so the name may be truncated.
+    TargetNameUnterminated(u8),
+
+    /// Target size in a target prefix does not match the size computed from
+    /// the elements.
+    TargetSizeMismatch {
+        /// Alternate setting of the image.
+        alt_setting: u8,
+
+        /// Size declared in the target prefix.
+        declared: u32,
+
+        /// Size computed from the elements.
+        computed: u64,
+    },
+
+    /// Element has a size of zero, which is often a sign of corruption.
+    EmptyElement {
+        /// Alternate setting of the image containing the element.
+        alt_setting: u8,
+
+        /// Start address of the element.
+        address: u32,
+    },
+
+    /// Element data extends beyond the end of the file.
+    ElementDataMissing {
+        /// Alternate setting of the image containing the element.
+        alt_setting: u8,
+
+        /// Start address of the element.
+        address: u32,
+    },
+
+    /// Size of the last element was clamped to the available data.
+    LastElementClamped {
+        /// Element size declared in the file.
+        declared_size: u32,
+    },
+
+    /// Number of bytes between the last element and the suffix.
+    TrailingGap(u64),
+
+    /// DFU specification number of a plain file is not a known revision
+    /// of the standard.
+    UnknownDfuSpecNo(u16),
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::CrcMismatch { stored, calculated } => write!(
+                f,
+                "CRC32 mismatch, stored 0x{stored:08X}, calculated 0x{calculated:08X}"
+            ),
+            Self::IncompleteContent(error) => write!(f, "Content is incomplete: {error}"),
+            Self::ImageSizeMismatch { declared, computed } => write!(
+                f,
+                "Image size mismatch, declared {declared} bytes, computed {computed}"
+            ),
+            Self::DuplicateAltSetting(alt_setting) => write!(
+                f,
+                "Alternate setting {alt_setting} is used by multiple images"
+            ),
+            Self::TargetNameGarbage(alt_setting) => write!(
+                f,
+                "Target name of alternate setting {alt_setting} is followed by garbage"
+            ),
+            Self::TargetNameUnterminated(alt_setting) => write!(
+                f,
+                "Target name of alternate setting {alt_setting} is not null-terminated and may be truncated"
+            ),
+            Self::TargetSizeMismatch {
+                alt_setting,
+                declared,
+                computed,
+            } => write!(
+                f,
+                "Target size mismatch for alternate setting {alt_setting}, declared {declared} bytes, computed {computed}"
+            ),
+            Self::EmptyElement {
+                alt_setting,
+                address,
+            } => write!(
+                f,
+                "Element at 0x{address:08X} in alternate setting {alt_setting} has a size of zero"
+            ),
+            Self::ElementDataMissing {
+                alt_setting,
+                address,
+            } => write!(
+                f,
+                "Data of element at 0x{address:08X} in alternate setting {alt_setting} exceeds the file"
+            ),
+            Self::LastElementClamped { declared_size } => write!(
+                f,
+                "Last element declares {declared_size} bytes, but the file ends early"
+            ),
+            Self::TrailingGap(size) => {
+                write!(f, "{size} bytes of data between last element and suffix")
+            }
+            Self::UnknownDfuSpecNo(spec_no) => write!(
+                f,
+                "DFU specification number 0x{spec_no:04X} is not a known DFU revision"
+            ),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Options to control the strictness of parsing.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Accept DfuSe files with a prefix version other than 1.
+    pub allow_unsupported_version: bool,
+
+    /// Clamp the size of the last element of a DfuSe file to the available
+    /// data if it extends into the suffix or beyond the end of the file.
+    pub allow_short_last_element: bool,
+
+    /// Reject DfuSe files containing elements with a size of zero.
+    pub reject_empty_elements: bool,
+
+    /// Reject DfuSe files containing an element larger than this size
+    /// in bytes.
+    pub max_element_size: Option<u32>,
+
+    /// Reject DfuSe files with a total size of all elements larger than
+    /// this size in bytes.
+    ///
+    /// Both limits are checked against the declared sizes while parsing
+    /// the headers, before any element data is read.
+    pub max_total_size: Option<u64>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Options to control writing of files.
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Keep data found between the last image element and the suffix
+    /// of a DfuSe file instead of dropping it.
+    pub preserve_trailing_data: bool,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
 /// Length of the file suffix in bytes.
-pub const SUFFIX_LENGTH: usize = 16;
+#[deprecated(since = "0.3.0", note = "use `Suffix::LENGTH` instead")]
+pub const SUFFIX_LENGTH: usize = Suffix::LENGTH;
 
 /// File suffix containing the metadata.
 #[allow(non_snake_case)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Suffix {
     /// Firmware version contained in the file, or 0xFFFF if ignored.
     pub bcdDevice: u16,
@@ -152,13 +1457,18 @@ pub struct Suffix {
     pub bcdDFU: u16,
 
     /// File identifier, must contain "DFU" in reversed order.
-    pub ucDFUSignature: String,
+    pub ucDFUSignature: [u8; 3],
 
-    /// Length of the suffix itself, fixed to 16.
+    /// Length of the suffix itself, 16 for the standard suffix or more
+    /// if it is extended by `vendor_data`.
     pub bLength: u8,
 
     /// Calculated CRC32 over the whole file except for the dwCRC data itself.
     pub dwCRC: u32,
+
+    /// Vendor-specific data placed in front of the standard suffix fields.
+    /// Only present when `bLength` is larger than 16.
+    pub vendor_data: Vec<u8>,
 }
 
 impl Default for Suffix {
@@ -169,21 +1479,25 @@ impl Default for Suffix {
             idProduct: 0xFFFF,
             idVendor: 0xFFFF,
             bcdDFU: 0x0100,
-            ucDFUSignature: String::from("UFD"),
-            bLength: SUFFIX_LENGTH as u8,
+            ucDFUSignature: *b"UFD",
+            bLength: Suffix::LENGTH as u8,
             dwCRC: 0,
+            vendor_data: Vec::new(),
         }
     }
 }
 
 impl Suffix {
+    /// Length of the standard suffix in bytes.
+    pub const LENGTH: usize = 16;
+
     /// Creates a new suffix.
     pub fn new(
         device_version: u16,
         product_id: u16,
         vendor_id: u16,
         dfu_spec_no: u16,
-        signature: String,
+        signature: [u8; 3],
         length: u8,
         crc: u32,
     ) -> Self {
@@ -195,36 +1509,313 @@ impl Suffix {
             ucDFUSignature: signature,
             bLength: length,
             dwCRC: crc,
+            vendor_data: Vec::new(),
         }
     }
 
+    /// Returns a builder for a new suffix.
+    pub fn builder() -> SuffixBuilder {
+        SuffixBuilder::new()
+    }
+
     /// Creates a new suffix from a buffer of u8 values.
-    pub fn from_bytes(buffer: &[u8; SUFFIX_LENGTH]) -> Self {
+    ///
+    /// The signature is stored as found and not validated, use
+    /// [`is_signature_valid()`](Self::is_signature_valid) to check it.
+    pub fn from_bytes(buffer: &[u8; Suffix::LENGTH]) -> Self {
         Self::new(
             u16::from_le_bytes([buffer[0], buffer[1]]),
             u16::from_le_bytes([buffer[2], buffer[3]]),
             u16::from_le_bytes([buffer[4], buffer[5]]),
             u16::from_le_bytes([buffer[6], buffer[7]]),
-            String::from_utf8_lossy(&buffer[8..11]).to_string(),
-            u8::from_le(buffer[11]),
+            [buffer[8], buffer[9], buffer[10]],
+            buffer[11],
             u32::from_le_bytes([buffer[12], buffer[13], buffer[14], buffer[15]]),
         )
     }
 
+    /// Returns the standard suffix fields as bytes in file order.
+    ///
+    /// The vendor data of an extended suffix is not included.
+    pub fn to_bytes(&self) -> [u8; Suffix::LENGTH] {
+        let mut buffer = [0; Suffix::LENGTH];
+        buffer[0..2].copy_from_slice(&self.bcdDevice.to_le_bytes());
+        buffer[2..4].copy_from_slice(&self.idProduct.to_le_bytes());
+        buffer[4..6].copy_from_slice(&self.idVendor.to_le_bytes());
+        buffer[6..8].copy_from_slice(&self.bcdDFU.to_le_bytes());
+
+        buffer[8..11].copy_from_slice(&self.ucDFUSignature);
+        buffer[11] = self.bLength;
+        buffer[12..16].copy_from_slice(&self.dwCRC.to_le_bytes());
+
+        buffer
+    }
+
     /// Creates a new suffix from reading a file.
-    pub fn from_file(file: &mut std::fs::File) -> Result<Self> {
-        file.seek(std::io::SeekFrom::End(-(SUFFIX_LENGTH as i64)))?;
-        let mut buffer = [0; SUFFIX_LENGTH];
+    ///
+    /// If `bLength` is larger than the standard length, the additional bytes
+    /// in front of the standard fields are read into `vendor_data`.
+    pub fn from_file<R: Read + Seek>(file: &mut R) -> Result<Self> {
+        file.seek(std::io::SeekFrom::End(-(Suffix::LENGTH as i64)))?;
+        let mut buffer = [0; Suffix::LENGTH];
         file.read_exact(&mut buffer)?;
 
-        let data = Self::from_bytes(&buffer);
+        let mut data = Self::from_bytes(&buffer);
+
+        if !data.is_signature_valid() {
+            return Err(anyhow!(Error::InvalidSuffixSignature));
+        }
+
+        if data.bLength as usize > Suffix::LENGTH {
+            let file_size = file.seek(std::io::SeekFrom::End(0))?;
+
+            // File must be at least as large as the extended suffix
+            if file_size < data.bLength as u64 {
+                return Err(anyhow!(Error::InsufficientFileSize));
+            }
+
+            file.seek(std::io::SeekFrom::End(-(data.bLength as i64)))?;
+            let mut vendor_data = vec![0; data.bLength as usize - Suffix::LENGTH];
+            file.read_exact(&mut vendor_data)?;
+            data.vendor_data = vendor_data;
+        }
+
+        Ok(data)
+    }
+
+    /// Creates a new suffix from reading a file asynchronously.
+    #[cfg(feature = "tokio")]
+    pub async fn from_file_async<R: AsyncRead + AsyncSeek + Unpin>(file: &mut R) -> Result<Self> {
+        file.seek(std::io::SeekFrom::End(-(Suffix::LENGTH as i64)))
+            .await?;
+        let mut buffer = [0; Suffix::LENGTH];
+        file.read_exact(&mut buffer).await?;
+
+        let mut data = Self::from_bytes(&buffer);
 
-        if &data.ucDFUSignature != "UFD" {
+        if !data.is_signature_valid() {
             return Err(anyhow!(Error::InvalidSuffixSignature));
         }
 
+        if data.bLength as usize > Suffix::LENGTH {
+            let file_size = file.seek(std::io::SeekFrom::End(0)).await?;
+
+            // File must be at least as large as the extended suffix
+            if file_size < data.bLength as u64 {
+                return Err(anyhow!(Error::InsufficientFileSize));
+            }
+
+            file.seek(std::io::SeekFrom::End(-(data.bLength as i64)))
+                .await?;
+            let mut vendor_data = vec![0; data.bLength as usize - Suffix::LENGTH];
+            file.read_exact(&mut vendor_data).await?;
+            data.vendor_data = vendor_data;
+        }
+
         Ok(data)
     }
+
+    /// Returns the signature as string, with invalid UTF-8 sequences replaced.
+    pub fn signature(&self) -> String {
+        String::from_utf8_lossy(&self.ucDFUSignature).to_string()
+    }
+
+    /// Check if the signature contains "DFU" in reversed order.
+    pub fn is_signature_valid(&self) -> bool {
+        &self.ucDFUSignature == b"UFD"
+    }
+
+    /// Resets the signature to "DFU" in reversed order.
+    pub fn normalize_signature(&mut self) {
+        self.ucDFUSignature = *b"UFD";
+    }
+
+    /// Returns the firmware version decoded from the BCD encoded `bcdDevice`
+    /// as major and minor number, e.g. `(1, 20)` for 0x0120.
+    ///
+    /// Returns `None` for the wildcard value 0xFFFF and for invalid BCD digits.
+    pub fn firmware_version(&self) -> Option<(u8, u8)> {
+        if self.bcdDevice == 0xFFFF {
+            return None;
+        }
+
+        let decode = |byte: u8| {
+            let (high, low) = (byte >> 4, byte & 0x0F);
+            (high <= 9 && low <= 9).then(|| high * 10 + low)
+        };
+
+        let [major, minor] = self.bcdDevice.to_be_bytes();
+
+        Some((decode(major)?, decode(minor)?))
+    }
+
+    /// Returns the BCD encoded device version.
+    pub fn device_version(&self) -> u16 {
+        self.bcdDevice
+    }
+
+    /// Returns the BCD encoded DFU specification number.
+    pub fn dfu_spec_no(&self) -> u16 {
+        self.bcdDFU
+    }
+
+    /// Check if the DFU specification number is a known revision
+    /// of the standard, i.e. DFU 1.0 (0x0100) or DFU 1.1 (0x0110).
+    ///
+    /// DfuSe files use 0x011A instead, which is not accepted here.
+    pub fn is_plain_dfu_revision(&self) -> bool {
+        matches!(self.bcdDFU, 0x0100 | 0x0110)
+    }
+
+    /// Returns the suffix length in bytes as stored in `bLength`.
+    pub fn length(&self) -> u8 {
+        self.bLength
+    }
+
+    /// Returns the stored CRC.
+    pub fn crc(&self) -> u32 {
+        self.dwCRC
+    }
+
+    /// Returns the intended vendor id or `None` if the field is ignored.
+    pub fn vendor_id(&self) -> Option<UsbId> {
+        UsbId::from_field(self.idVendor)
+    }
+
+    /// Returns the intended product id or `None` if the field is ignored.
+    pub fn product_id(&self) -> Option<UsbId> {
+        UsbId::from_field(self.idProduct)
+    }
+
+    /// Check if the file is intended for a device with given vendor and product id.
+    ///
+    /// Fields set to the wildcard value 0xFFFF always match.
+    pub fn matches_device(&self, vendor: u16, product: u16) -> bool {
+        (self.idVendor == 0xFFFF || self.idVendor == vendor)
+            && (self.idProduct == 0xFFFF || self.idProduct == product)
+    }
+
+    /// Returns the length of the suffix in bytes including the vendor data.
+    pub fn total_length(&self) -> usize {
+        Suffix::LENGTH + self.vendor_data.len()
+    }
+
+    /// Returns the suffix with `dwCRC` calculated for a file consisting of
+    /// `preceding_data` followed by the suffix.
+    ///
+    /// As required by the DFU specification, the CRC covers not only the
+    /// preceding data but also the suffix itself except for `dwCRC`,
+    /// including the vendor data of an extended suffix.
+    pub fn with_computed_crc(mut self, preceding_data: &[u8]) -> Suffix {
+        let mut crc = crc32::Crc32::new();
+        crc.update(preceding_data);
+        crc.update(&self.vendor_data);
+        crc.update(&self.to_bytes()[..Suffix::LENGTH - 4]);
+        self.dwCRC = crc.finalize() ^ 0xFFFFFFFF_u32;
+
+        self
+    }
+}
+
+impl TryFrom<&[u8]> for Suffix {
+    type Error = Error;
+
+    /// Creates a new suffix from a slice, which must have the suffix length.
+    fn try_from(buffer: &[u8]) -> Result<Self, Self::Error> {
+        let buffer = buffer.try_into().map_err(|_| Error::LengthMismatch {
+            expected: Suffix::LENGTH,
+            actual: buffer.len(),
+        })?;
+
+        Ok(Self::from_bytes(buffer))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// Builder for a suffix.
+///
+/// Fields not set explicitly keep the values of [`Suffix::default`], so the
+/// signature and length are always correct. The CRC is left at 0, as it
+/// depends on the file content and is filled in when writing the file.
+#[derive(Debug, Clone, Default)]
+pub struct SuffixBuilder {
+    /// Suffix under construction.
+    suffix: Suffix,
+}
+
+impl SuffixBuilder {
+    /// Creates a new builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the firmware version.
+    pub fn device_version(mut self, version: u16) -> Self {
+        self.suffix.bcdDevice = version;
+        self
+    }
+
+    /// Sets the intended vendor id of the device.
+    pub fn vendor(mut self, id: u16) -> Self {
+        self.suffix.idVendor = id;
+        self
+    }
+
+    /// Sets the intended product id of the device.
+    pub fn product(mut self, id: u16) -> Self {
+        self.suffix.idProduct = id;
+        self
+    }
+
+    /// Sets the DFU specification number, e.g. 0x011A for DfuSe files.
+    pub fn dfu_spec_no(mut self, spec_no: u16) -> Self {
+        self.suffix.bcdDFU = spec_no;
+        self
+    }
+
+    /// Returns the suffix.
+    pub fn build(self) -> Suffix {
+        self.suffix
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+
+/// USB vendor or product id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UsbId(pub u16);
+
+impl UsbId {
+    /// Creates a new id from a suffix field, returning `None` for the
+    /// wildcard value 0xFFFF.
+    pub fn from_field(value: u16) -> Option<Self> {
+        if value == 0xFFFF {
+            None
+        } else {
+            Some(Self(value))
+        }
+    }
+
+    /// Returns the name of a well-known vendor if the id is a vendor id.
+    pub fn vendor_name(&self) -> Option<&'static str> {
+        match self.0 {
+            0x0483 => Some("STMicroelectronics"),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for UsbId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "0x{:04x}", self.0)
+    }
+}
+
+impl From<UsbId> for u16 {
+    fn from(id: UsbId) -> Self {
+        id.0
+    }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -237,19 +1828,60 @@ pub enum Error {
 
     /// File is too small (smaller than suffix size).
     InsufficientFileSize,
+
+    /// Operation is only supported for files with plain content.
+    NotPlainContent,
+
+    /// Operation is only supported for files with DfuSe content.
+    NotDfuSeContent,
+
+    /// No image with the alternate setting exists.
+    UnknownAltSetting(u8),
+
+    /// Archive ends with an incomplete DFU file.
+    TruncatedArchive {
+        /// Offset of the incomplete file inside the archive.
+        offset: u64,
+    },
+
+    /// Buffer length does not match the required length.
+    LengthMismatch {
+        /// Required length in bytes.
+        expected: usize,
+
+        /// Actual length in bytes.
+        actual: usize,
+    },
+
+    /// Suffix with the given length in bytes including the vendor data
+    /// exceeds the maximum of 255 that can be stored in `bLength`.
+    SuffixTooLong(usize),
 }
 
 impl std::error::Error for Error {}
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Self::InvalidSuffixSignature => "Invalid file suffix signature",
-                Self::InsufficientFileSize => "File size is to small to contain suffix",
+        match self {
+            Self::InvalidSuffixSignature => write!(f, "Invalid file suffix signature"),
+            Self::InsufficientFileSize => write!(f, "File size is to small to contain suffix"),
+            Self::NotPlainContent => write!(f, "File content is not plain"),
+            Self::NotDfuSeContent => write!(f, "File content is not DfuSe"),
+            Self::UnknownAltSetting(alt_setting) => {
+                write!(f, "No image with alternate setting {alt_setting}")
             }
-        )
+            Self::TruncatedArchive { offset } => {
+                write!(f, "Incomplete DFU file at offset {offset} of archive")
+            }
+            Self::LengthMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Length mismatch, expected {expected} bytes, got {actual}"
+                )
+            }
+            Self::SuffixTooLong(length) => {
+                write!(f, "Suffix of {length} bytes exceeds the maximum of 255")
+            }
+        }
     }
 }
@@ -0,0 +1,183 @@
+//! Helpers to build DFU files in memory for testing.
+
+#![allow(dead_code)]
+
+use std::io::Write;
+
+use dfufile::{dfuse, Content, DfuFile};
+
+/// Vendor id used in the fixtures.
+pub const VENDOR_ID: u16 = 0x0483;
+
+/// Product id used in the fixtures.
+pub const PRODUCT_ID: u16 = 0xDF11;
+
+/// Device version used in the fixtures.
+pub const DEVICE_VERSION: u16 = 0x0200;
+
+/// Returns an image element with a declared size that may differ
+/// from the length of the data.
+pub fn element_bytes(address: u32, declared_size: u32, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&address.to_le_bytes());
+    bytes.extend_from_slice(&declared_size.to_le_bytes());
+    bytes.extend_from_slice(data);
+
+    bytes
+}
+
+/// Returns a target prefix followed by the given image elements.
+pub fn target_bytes(alt_setting: u8, name: &str, elements: &[Vec<u8>]) -> Vec<u8> {
+    let target_size = elements.iter().map(Vec::len).sum::<usize>() as u32;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"Target");
+    bytes.push(alt_setting);
+    bytes.extend_from_slice(&(!name.is_empty() as u32).to_le_bytes());
+    let mut name_field = name.as_bytes().to_vec();
+    name_field.resize(255, 0);
+    bytes.extend_from_slice(&name_field);
+    bytes.extend_from_slice(&target_size.to_le_bytes());
+    bytes.extend_from_slice(&(elements.len() as u32).to_le_bytes());
+
+    for element in elements {
+        bytes.extend_from_slice(element);
+    }
+
+    bytes
+}
+
+/// Returns a DfuSe image consisting of the prefix and the given targets,
+/// without suffix.
+pub fn dfuse_bytes(targets: &[Vec<u8>]) -> Vec<u8> {
+    let image_size = 11 + targets.iter().map(Vec::len).sum::<usize>() as u32;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"DfuSe");
+    bytes.push(1);
+    bytes.extend_from_slice(&image_size.to_le_bytes());
+    bytes.push(targets.len() as u8);
+
+    for target in targets {
+        bytes.extend_from_slice(target);
+    }
+
+    bytes
+}
+
+/// Appends a suffix with the fixture ids and a valid CRC.
+pub fn with_suffix(mut bytes: Vec<u8>, dfu_spec_no: u16) -> Vec<u8> {
+    bytes.extend_from_slice(&DEVICE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&PRODUCT_ID.to_le_bytes());
+    bytes.extend_from_slice(&VENDOR_ID.to_le_bytes());
+    bytes.extend_from_slice(&dfu_spec_no.to_le_bytes());
+    bytes.extend_from_slice(b"UFD");
+    bytes.push(16);
+
+    let crc = dfufile::crc32::crc32(&bytes, 0) ^ 0xFFFFFFFF;
+    bytes.extend_from_slice(&crc.to_le_bytes());
+
+    bytes
+}
+
+/// Returns a complete DfuSe file with the given targets.
+pub fn dfuse_file_bytes(targets: &[Vec<u8>]) -> Vec<u8> {
+    with_suffix(dfuse_bytes(targets), 0x011A)
+}
+
+/// Returns a plain file with the given payload.
+pub fn plain_file_bytes(payload: &[u8]) -> Vec<u8> {
+    with_suffix(payload.to_vec(), 0x0100)
+}
+
+/// Returns test data of a given length with a recognizable pattern.
+pub fn pattern(len: usize, seed: u8) -> Vec<u8> {
+    (0..len).map(|i| (i as u8).wrapping_mul(7) ^ seed).collect()
+}
+
+/// Returns a DfuSe file with a single image containing two elements.
+pub fn single_image_fixture() -> Vec<u8> {
+    dfuse_file_bytes(&[target_bytes(
+        0,
+        "Internal Flash",
+        &[
+            element_bytes(0x0800_0000, 512, &pattern(512, 0x11)),
+            element_bytes(0x0800_4000, 256, &pattern(256, 0x22)),
+        ],
+    )])
+}
+
+/// Returns a DfuSe file with a flash image and an option bytes image.
+pub fn multi_image_fixture() -> Vec<u8> {
+    dfuse_file_bytes(&[
+        target_bytes(
+            0,
+            "Internal Flash",
+            &[element_bytes(0x0800_0000, 1024, &pattern(1024, 0x33))],
+        ),
+        target_bytes(
+            1,
+            "Option Bytes",
+            &[element_bytes(0x1FFF_C000, 16, &pattern(16, 0x44))],
+        ),
+    ])
+}
+
+/// File in the temporary directory that is deleted when dropped,
+/// even if the test fails.
+pub struct TempFile(std::path::PathBuf);
+
+impl TempFile {
+    /// Returns a guard for a file with a name unique to the test process.
+    /// The file itself is not created.
+    pub fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("dfufile-{}-{name}.dfu", std::process::id())))
+    }
+
+    /// Returns the path to the file.
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        // The file may not exist if the test failed before creating it
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Writes the bytes to a file in the temporary directory and returns
+/// a guard deleting it.
+pub fn write_temp_file(name: &str, bytes: &[u8]) -> TempFile {
+    let temp_file = TempFile::new(name);
+    std::fs::File::create(temp_file.path())
+        .unwrap()
+        .write_all(bytes)
+        .unwrap();
+
+    temp_file
+}
+
+/// Writes the bytes to a temporary file and opens it.
+///
+/// The file is deleted when the returned guard is dropped.
+pub fn open_temp_file(name: &str, bytes: &[u8]) -> (TempFile, DfuFile) {
+    let temp_file = write_temp_file(name, bytes);
+    let dfu_file = DfuFile::open(temp_file.path()).unwrap();
+
+    (temp_file, dfu_file)
+}
+
+/// Returns the DfuSe content of a file.
+pub fn dfuse_content(dfu_file: &DfuFile) -> &dfuse::Content {
+    match &dfu_file.content {
+        Content::DfuSe(content) => content,
+        Content::Plain => panic!("Not a DfuSe file"),
+    }
+}
+
+/// Returns the first element of the first image of a DfuSe file.
+pub fn first_element(dfu_file: &DfuFile) -> dfuse::ImageElement {
+    dfuse_content(dfu_file).images[0].image_elements[0].clone()
+}
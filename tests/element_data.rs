@@ -0,0 +1,56 @@
+//! Tests for reading element data from files with inconsistent sizes.
+
+mod common;
+
+/// Builds a DfuSe file with a single element holding `data`, while the
+/// element header declares `declared_size` bytes.
+fn build_file(data: &[u8], declared_size: u32) -> Vec<u8> {
+    common::dfuse_file_bytes(&[common::target_bytes(
+        0,
+        "Internal Flash",
+        &[common::element_bytes(0x0800_0000, declared_size, data)],
+    )])
+}
+
+#[test]
+fn element_data_present() {
+    let (_temp_file, mut dfu_file) =
+        common::open_temp_file("data-present", &build_file(&[0xAA; 64], 64));
+    let element = common::first_element(&dfu_file);
+
+    assert!(element.verify_data_present(&mut dfu_file.file).unwrap());
+
+    let mut buffer = [0; 128];
+    let read_size = element.read_at(&mut dfu_file.file, 0, &mut buffer).unwrap();
+    assert_eq!(read_size, 64);
+    assert!(buffer[..64].iter().all(|byte| *byte == 0xAA));
+
+    let read_size = element
+        .read_at(&mut dfu_file.file, 60, &mut buffer)
+        .unwrap();
+    assert_eq!(read_size, 4);
+
+    let read_size = element
+        .read_at(&mut dfu_file.file, 100, &mut buffer)
+        .unwrap();
+    assert_eq!(read_size, 0);
+}
+
+#[test]
+fn element_size_beyond_eof() {
+    let (_temp_file, mut dfu_file) =
+        common::open_temp_file("size-beyond-eof", &build_file(&[0xAA; 10], 1000));
+    let element = common::first_element(&dfu_file);
+    let file_size = dfu_file.file.metadata().unwrap().len();
+
+    assert!(!element.verify_data_present(&mut dfu_file.file).unwrap());
+
+    let mut buffer = [0; 2000];
+    let read_size = element.read_at(&mut dfu_file.file, 0, &mut buffer).unwrap();
+    assert_eq!(read_size as u64, file_size - element.data_position);
+
+    let read_size = element
+        .read_at(&mut dfu_file.file, 500, &mut buffer)
+        .unwrap();
+    assert_eq!(read_size, 0);
+}
@@ -0,0 +1,67 @@
+//! Tests for decoding and encoding the multi-byte header fields in
+//! little-endian order, independent of the host byte order.
+
+use dfufile::dfuse::{ImageElement, Prefix, TargetPrefix};
+use dfufile::Suffix;
+
+#[test]
+fn suffix() {
+    let buffer = [
+        0x34, 0x12, 0x11, 0xDF, 0x83, 0x04, 0x1A, 0x01, b'U', b'F', b'D', 0x10, 0x78, 0x56, 0x34,
+        0x12,
+    ];
+    let suffix = Suffix::from_bytes(&buffer);
+
+    assert_eq!(suffix.bcdDevice, 0x1234);
+    assert_eq!(suffix.idProduct, 0xDF11);
+    assert_eq!(suffix.idVendor, 0x0483);
+    assert_eq!(suffix.bcdDFU, 0x011A);
+    assert!(suffix.is_signature_valid());
+    assert_eq!(suffix.bLength, 16);
+    assert_eq!(suffix.dwCRC, 0x1234_5678);
+    assert_eq!(suffix.to_bytes(), buffer);
+}
+
+#[test]
+fn prefix() {
+    let buffer = [
+        b'D', b'f', b'u', b'S', b'e', 0x01, 0x78, 0x56, 0x34, 0x12, 0x02,
+    ];
+    let prefix = Prefix::from_bytes(&buffer);
+
+    assert_eq!(prefix.szSignature, "DfuSe");
+    assert_eq!(prefix.bVersion, 1);
+    assert_eq!(prefix.DFUImageSize, 0x1234_5678);
+    assert_eq!(prefix.bTargets, 2);
+    assert_eq!(prefix.to_bytes(), buffer);
+}
+
+#[test]
+fn target_prefix() {
+    let mut buffer = [0; TargetPrefix::LENGTH];
+    buffer[0..6].copy_from_slice(b"Target");
+    buffer[6] = 0x03;
+    buffer[7..11].copy_from_slice(&[0x01, 0x00, 0x00, 0x00]);
+    buffer[11..16].copy_from_slice(b"Flash");
+    buffer[266..270].copy_from_slice(&[0x04, 0x03, 0x02, 0x01]);
+    buffer[270..274].copy_from_slice(&[0x02, 0x01, 0x00, 0x00]);
+    let target_prefix = TargetPrefix::from_bytes(&buffer);
+
+    assert_eq!(target_prefix.szSignature, "Target");
+    assert_eq!(target_prefix.bAlternateSetting, 3);
+    assert!(target_prefix.is_named());
+    assert_eq!(target_prefix.szTargetName, "Flash");
+    assert_eq!(target_prefix.dwTargetSize, 0x0102_0304);
+    assert_eq!(target_prefix.dwNbElements, 0x0102);
+    assert_eq!(target_prefix.to_bytes(), buffer);
+}
+
+#[test]
+fn image_element() {
+    let buffer = [0x00, 0x40, 0x00, 0x08, 0x00, 0x02, 0x00, 0x00];
+    let element = ImageElement::from_bytes(&buffer, 0);
+
+    assert_eq!(element.dwElementAddress, 0x0800_4000);
+    assert_eq!(element.dwElementSize, 0x0200);
+    assert_eq!(element.to_bytes(), buffer);
+}
@@ -0,0 +1,191 @@
+//! Parsing tests using DfuSe and plain fixtures built in memory.
+
+mod common;
+
+use dfufile::{dfuse, Content, DfuFile};
+
+/// Checks the suffix fields against the fixture values.
+fn assert_suffix(dfu_file: &mut DfuFile, dfu_spec_no: u16) {
+    let suffix = dfu_file.suffix.clone();
+
+    assert_eq!(suffix.bcdDevice, common::DEVICE_VERSION);
+    assert_eq!(suffix.idProduct, common::PRODUCT_ID);
+    assert_eq!(suffix.idVendor, common::VENDOR_ID);
+    assert_eq!(suffix.bcdDFU, dfu_spec_no);
+    assert_eq!(suffix.signature(), "UFD");
+    assert!(suffix.is_signature_valid());
+    assert_eq!(suffix.bLength, 16);
+    assert!(suffix.vendor_data.is_empty());
+    assert_eq!(dfu_file.calc_crc().unwrap(), suffix.dwCRC);
+}
+
+/// Checks an element header and its data.
+fn assert_element(
+    dfu_file: &mut DfuFile,
+    element: &dfuse::ImageElement,
+    address: u32,
+    data_position: u64,
+    data: &[u8],
+) {
+    assert_eq!(element.dwElementAddress, address);
+    assert_eq!(element.dwElementSize, data.len() as u32);
+    assert_eq!(element.data_position, data_position);
+    assert_eq!(element.read_all(&mut dfu_file.file).unwrap(), data);
+}
+
+#[test]
+fn single_image() {
+    let bytes = common::single_image_fixture();
+    let (_temp_file, mut dfu_file) = common::open_temp_file("single-image", &bytes);
+    let content = common::dfuse_content(&dfu_file).images.clone();
+
+    assert_eq!(dfu_file.content.to_string(), "DfuSe v1");
+
+    let prefix = &common::dfuse_content(&dfu_file).prefix;
+    assert_eq!(prefix.szSignature, "DfuSe");
+    assert_eq!(prefix.bVersion, 1);
+    assert_eq!(prefix.DFUImageSize as usize, bytes.len() - 16);
+    assert_eq!(prefix.bTargets, 1);
+
+    assert_eq!(content.len(), 1);
+    let target_prefix = &content[0].target_prefix;
+    assert_eq!(target_prefix.szSignature, "Target");
+    assert_eq!(target_prefix.bAlternateSetting, 0);
+    assert_eq!(target_prefix.bTargetNamed, 1);
+    assert_eq!(target_prefix.szTargetName, "Internal Flash");
+    assert_eq!(target_prefix.dwTargetSize, 8 + 512 + 8 + 256);
+    assert_eq!(target_prefix.dwNbElements, 2);
+
+    let elements = &content[0].image_elements;
+    assert_eq!(elements.len(), 2);
+    assert_element(
+        &mut dfu_file,
+        &elements[0],
+        0x0800_0000,
+        11 + 274 + 8,
+        &common::pattern(512, 0x11),
+    );
+    assert_element(
+        &mut dfu_file,
+        &elements[1],
+        0x0800_4000,
+        11 + 274 + 8 + 512 + 8,
+        &common::pattern(256, 0x22),
+    );
+
+    assert_suffix(&mut dfu_file, 0x011A);
+}
+
+#[test]
+fn multi_image() {
+    let bytes = common::multi_image_fixture();
+    let (_temp_file, mut dfu_file) = common::open_temp_file("multi-image", &bytes);
+    let content = common::dfuse_content(&dfu_file).images.clone();
+
+    let prefix = &common::dfuse_content(&dfu_file).prefix;
+    assert_eq!(prefix.szSignature, "DfuSe");
+    assert_eq!(prefix.bVersion, 1);
+    assert_eq!(prefix.DFUImageSize as usize, bytes.len() - 16);
+    assert_eq!(prefix.bTargets, 2);
+
+    assert_eq!(content.len(), 2);
+
+    let target_prefix = &content[0].target_prefix;
+    assert_eq!(target_prefix.szSignature, "Target");
+    assert_eq!(target_prefix.bAlternateSetting, 0);
+    assert_eq!(target_prefix.bTargetNamed, 1);
+    assert_eq!(target_prefix.szTargetName, "Internal Flash");
+    assert_eq!(target_prefix.dwTargetSize, 8 + 1024);
+    assert_eq!(target_prefix.dwNbElements, 1);
+    assert_element(
+        &mut dfu_file,
+        &content[0].image_elements[0],
+        0x0800_0000,
+        11 + 274 + 8,
+        &common::pattern(1024, 0x33),
+    );
+
+    let target_prefix = &content[1].target_prefix;
+    assert_eq!(target_prefix.szSignature, "Target");
+    assert_eq!(target_prefix.bAlternateSetting, 1);
+    assert_eq!(target_prefix.bTargetNamed, 1);
+    assert_eq!(target_prefix.szTargetName, "Option Bytes");
+    assert_eq!(target_prefix.dwTargetSize, 8 + 16);
+    assert_eq!(target_prefix.dwNbElements, 1);
+    assert_element(
+        &mut dfu_file,
+        &content[1].image_elements[0],
+        0x1FFF_C000,
+        11 + 274 + 8 + 1024 + 274 + 8,
+        &common::pattern(16, 0x44),
+    );
+
+    assert_suffix(&mut dfu_file, 0x011A);
+}
+
+#[test]
+fn plain() {
+    let payload = common::pattern(300, 0x55);
+    let (_temp_file, mut dfu_file) =
+        common::open_temp_file("plain", &common::plain_file_bytes(&payload));
+
+    assert_eq!(dfu_file.content.to_string(), "Plain");
+    assert_eq!(dfu_file.plain_payload().unwrap(), payload);
+
+    assert_suffix(&mut dfu_file, 0x0100);
+}
+
+#[test]
+fn dfuse_round_trip() {
+    for (name, bytes) in [
+        ("round-trip-single", common::single_image_fixture()),
+        ("round-trip-multi", common::multi_image_fixture()),
+    ] {
+        let (
+            _temp_file,
+            DfuFile {
+                mut file,
+                content,
+                suffix,
+                ..
+            },
+        ) = common::open_temp_file(name, &bytes);
+
+        let content = match content {
+            Content::DfuSe(content) => content,
+            Content::Plain => panic!("Not a DfuSe file"),
+        };
+
+        let mut written = Vec::new();
+        let written_size = content
+            .write_to(&mut written, |element| element.read_all(&mut file))
+            .unwrap();
+        assert_eq!(written_size, written.len() as u64);
+        written.extend_from_slice(&suffix.to_bytes());
+
+        assert_eq!(written, bytes);
+    }
+}
+
+#[test]
+fn create_empty_dfuse() {
+    let temp_file = common::TempFile::new("empty");
+    let mut dfu_file = DfuFile::create(
+        temp_file.path(),
+        dfuse::Content::empty(),
+        Default::default(),
+    )
+    .unwrap();
+
+    let content = common::dfuse_content(&dfu_file);
+    assert_eq!(content.prefix.bTargets, 0);
+    assert_eq!(content.prefix.DFUImageSize, dfuse::Prefix::LENGTH as u32);
+    assert!(content.images.is_empty());
+    assert_eq!(dfu_file.suffix.bcdDFU, 0x011A);
+
+    let report = dfu_file.analyze().unwrap();
+    assert!(report.crc_valid);
+    assert!(report.declared_size_matches);
+    assert_eq!(report.missing_bytes, 0);
+    assert_eq!(report.trailing_gap, 0);
+}
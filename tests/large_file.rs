@@ -0,0 +1,74 @@
+//! Tests for files larger than 4 GiB, using sparse files so that no real
+//! data has to be written.
+//!
+//! Not all file systems support sparse files, so these tests are ignored by
+//! default. Run them with `cargo test -- --ignored`.
+
+mod common;
+
+use std::io::{Seek, SeekFrom, Write};
+
+use dfufile::{Content, DfuFile, Suffix};
+
+/// Declared size of the first element, close to the limit of the size field.
+const LARGE_ELEMENT_SIZE: u32 = 0xFFFF_FF00;
+
+#[test]
+#[ignore = "creates a sparse file of more than 4 GiB"]
+fn offsets_beyond_4gib() {
+    let small_data = common::pattern(64, 0x66);
+
+    let first_target = common::target_bytes(
+        0,
+        "Internal Flash",
+        &[common::element_bytes(0x0800_0000, LARGE_ELEMENT_SIZE, &[])],
+    );
+    let second_target = common::target_bytes(
+        1,
+        "External Flash",
+        &[common::element_bytes(0x9000_0000, 64, &small_data)],
+    );
+
+    // The prefix is patched with the actual sizes, the headers of the first
+    // target are written, then the hole for the element data is skipped.
+    let mut head = common::dfuse_bytes(&[first_target]);
+    head[10] = 2;
+
+    let temp_file = common::write_temp_file("large", &head);
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(temp_file.path())
+        .unwrap();
+    file.seek(SeekFrom::Start(
+        head.len() as u64 + LARGE_ELEMENT_SIZE as u64,
+    ))
+    .unwrap();
+    let mut tail = second_target;
+    tail.extend_from_slice(&Suffix::builder().dfu_spec_no(0x011A).build().to_bytes());
+    file.write_all(&tail).unwrap();
+    drop(file);
+
+    let mut dfu_file = DfuFile::open(temp_file.path()).unwrap();
+
+    let content = match &dfu_file.content {
+        Content::DfuSe(content) => content.clone(),
+        Content::Plain => panic!("Not a DfuSe file"),
+    };
+
+    let first_element = &content.images[0].image_elements[0];
+    assert_eq!(first_element.dwElementSize, LARGE_ELEMENT_SIZE);
+    assert_eq!(first_element.data_position, 11 + 274 + 8);
+
+    let second_element = &content.images[1].image_elements[0];
+    let expected_position = 11 + 274 + 8 + LARGE_ELEMENT_SIZE as u64 + 274 + 8;
+    assert!(second_element.data_position > u32::MAX as u64);
+    assert_eq!(second_element.data_position, expected_position);
+    assert_eq!(
+        second_element.read_all(&mut dfu_file.file).unwrap(),
+        small_data
+    );
+
+    let file_size = dfu_file.file.metadata().unwrap().len();
+    assert_eq!(content.computed_file_size(), file_size);
+    assert_eq!(content.trailing_gap(file_size), 0);
+}
@@ -0,0 +1,72 @@
+//! Tests for decoding the target name field of target prefixes.
+
+use dfufile::dfuse::TargetPrefix;
+
+/// Returns a target prefix buffer with the given raw name field content.
+fn target_prefix_buffer(name_field: &[u8]) -> [u8; TargetPrefix::LENGTH] {
+    let mut buffer = [0; TargetPrefix::LENGTH];
+    buffer[0..6].copy_from_slice(b"Target");
+    buffer[7] = 1;
+    buffer[11..11 + name_field.len()].copy_from_slice(name_field);
+
+    buffer
+}
+
+#[test]
+fn high_bytes_before_null() {
+    let buffer = target_prefix_buffer(b"Fl\xC3\xA4sh \xFF\xFE\x00garbage\xFF");
+    let target_prefix = TargetPrefix::from_bytes(&buffer);
+
+    assert_eq!(target_prefix.szTargetName, "Fl\u{E4}sh \u{FFFD}\u{FFFD}");
+}
+
+#[test]
+fn high_bytes_without_null() {
+    let mut name_field = vec![b'A'];
+    name_field.resize(255, 0xFF);
+    let buffer = target_prefix_buffer(&name_field);
+    let target_prefix = TargetPrefix::from_bytes(&buffer);
+
+    assert!(target_prefix.szTargetName.starts_with('A'));
+    assert_eq!(target_prefix.szTargetName.chars().count(), 255);
+}
+
+/// Returns the name after writing it with `set_name` and reading it back.
+fn round_trip(name: &str) -> String {
+    let mut target_prefix = TargetPrefix::default();
+    target_prefix.set_name(name).unwrap();
+    let buffer = target_prefix.to_bytes();
+
+    TargetPrefix::from_bytes(&buffer).szTargetName
+}
+
+#[test]
+fn set_name_round_trip() {
+    assert_eq!(round_trip("Internal Flash"), "Internal Flash");
+    assert_eq!(round_trip("Fl\u{E4}sh \u{1F4BE}"), "Fl\u{E4}sh \u{1F4BE}");
+    assert_eq!(round_trip(""), "");
+}
+
+#[test]
+fn set_name_longest_name() {
+    let name = "N".repeat(254);
+
+    assert_eq!(round_trip(&name), name);
+}
+
+#[test]
+fn set_name_rejects_null() {
+    let mut target_prefix = TargetPrefix::default();
+
+    assert!(target_prefix.set_name("Internal\0Flash").is_err());
+    assert_eq!(target_prefix.szTargetName, "");
+}
+
+#[test]
+fn set_name_rejects_long_name() {
+    let mut target_prefix = TargetPrefix::default();
+
+    assert!(target_prefix.set_name(&"N".repeat(255)).is_err());
+    assert!(target_prefix.set_name(&"\u{E4}".repeat(128)).is_err());
+    assert_eq!(target_prefix.szTargetName, "");
+}